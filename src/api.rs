@@ -1,8 +1,108 @@
-use crate::models::ContributionCalendar;
+use crate::config::{Network, Profile, Provider};
+use crate::models::{ContributionCalendar, ContributionDay, Week};
+use crate::stats;
 use anyhow::{Context, Result};
-use reqwest::Client;
+use chrono::{DateTime, Datelike, Days, Local, TimeDelta, Utc};
+use reqwest::{Client, Proxy, Response, StatusCode, header::HeaderMap};
+use std::{collections::HashMap, error, fmt};
 
-pub async fn fetch_contributions(token: &str, username: &str) -> Result<ContributionCalendar> {
+pub const GITHUB_HOST: &str = "github.com";
+pub const GITLAB_HOST: &str = "gitlab.com";
+
+pub async fn fetch_contributions(
+    profile: &Profile,
+    token: &str,
+    network: &Network,
+) -> Result<ContributionCalendar> {
+    let client = client(network)?;
+
+    match profile.provider {
+        Provider::GitHub => fetch_github(&client, profile.host(), token, &profile.username).await,
+        Provider::GitLab => fetch_gitlab(&client, profile.host(), token, &profile.username).await,
+    }
+}
+
+/// Returned when the API refuses requests until `until`.
+#[derive(Debug)]
+pub struct RateLimited {
+    pub until: DateTime<Utc>,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Rate limited until {}",
+            self.until.with_timezone(&Local).format("%H:%M:%S")
+        )
+    }
+}
+
+impl error::Error for RateLimited {}
+
+/// Turns a rate-limit response into [`RateLimited`], leaving other errors
+/// to the caller.
+fn check_rate_limit(response: &Response) -> Result<()> {
+    match rate_limit(response.status(), response.headers()) {
+        Some(limit) => Err(limit.into()),
+        None => Ok(()),
+    }
+}
+
+/// A 429, or a 403 that says no requests are left. GitHub sends
+/// `X-RateLimit-Reset` with every response, so a 403 for a bad token or a
+/// private profile is not mistaken for a rate limit. The wait comes from
+/// `Retry-After` or the reset timestamp both GitHub (`X-RateLimit-Reset`)
+/// and GitLab (`RateLimit-Reset`) send.
+fn rate_limit(status: StatusCode, headers: &HeaderMap) -> Option<RateLimited> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<i64>().ok())
+    };
+
+    let retry_after = header("retry-after");
+    let exhausted = header("x-ratelimit-remaining")
+        .or(header("ratelimit-remaining"))
+        .is_some_and(|remaining| remaining == 0);
+
+    let limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN && (exhausted || retry_after.is_some()));
+    if !limited {
+        return None;
+    }
+
+    let until = match retry_after {
+        Some(seconds) => Some(Utc::now() + TimeDelta::seconds(seconds)),
+        None => header("x-ratelimit-reset")
+            .or(header("ratelimit-reset"))
+            .and_then(|reset| DateTime::from_timestamp(reset, 0)),
+    };
+
+    until.map(|until| RateLimited { until })
+}
+
+fn client(network: &Network) -> Result<Client> {
+    let mut builder = Client::builder();
+
+    if let Some(timeout) = network.timeout()? {
+        builder = builder.timeout(timeout.to_std().context("Invalid network.timeout")?);
+    }
+    if let Some(proxy) = &network.proxy {
+        builder = builder
+            .proxy(Proxy::all(proxy).with_context(|| format!("Invalid network.proxy {}", proxy))?);
+    }
+
+    builder.build().context("Failed to create HTTP client")
+}
+
+async fn fetch_github(
+    client: &Client,
+    host: &str,
+    token: &str,
+    username: &str,
+) -> Result<ContributionCalendar> {
     let query = r#"
         query($username:String!) {
             user(login: $username) {
@@ -26,15 +126,26 @@ pub async fn fetch_contributions(token: &str, username: &str) -> Result<Contribu
         "variables": { "username": username }
     });
 
-    let client = Client::new();
-    let response: serde_json::Value = client
-        .post("https://api.github.com/graphql")
+    // GitHub Enterprise Server serves the API under the instance itself.
+    let url = if host == GITHUB_HOST {
+        "https://api.github.com/graphql".to_string()
+    } else {
+        format!("https://{}/api/graphql", host)
+    };
+
+    let response = client
+        .post(url)
         .bearer_auth(token)
         .header("User-Agent", "trexanh")
         .json(&body)
         .send()
         .await
-        .context("Failed to send request to GitHub API")?
+        .context("Failed to send request to GitHub API")?;
+    check_rate_limit(&response)?;
+
+    let response: serde_json::Value = response
+        .error_for_status()
+        .context("GitHub API refused the request")?
         .json()
         .await
         .context("Failed to parse GitHub response as JSON")?;
@@ -49,3 +160,118 @@ pub async fn fetch_contributions(token: &str, username: &str) -> Result<Contribu
 
     Ok(calendar)
 }
+
+/// GitLab only reports days with activity, so the rolling year is rebuilt
+/// here as Sunday-aligned weeks like GitHub's calendar.
+async fn fetch_gitlab(
+    client: &Client,
+    host: &str,
+    token: &str,
+    username: &str,
+) -> Result<ContributionCalendar> {
+    let mut request = client
+        .get(format!("https://{}/users/{}/calendar.json", host, username))
+        .header("User-Agent", "trexanh");
+    if !token.is_empty() {
+        request = request.header("PRIVATE-TOKEN", token);
+    }
+
+    let response = request
+        .send()
+        .await
+        .context("Failed to send request to GitLab")?;
+    check_rate_limit(&response)?;
+
+    let counts: HashMap<String, u32> = response
+        .error_for_status()
+        .with_context(|| format!("Failed to fetch the calendar of @{}", username))?
+        .json()
+        .await
+        .context("Failed to parse GitLab response as JSON")?;
+
+    let today = stats::today_date();
+    let days_since_sunday = today.weekday().num_days_from_sunday() as u64;
+    let mut date = today - Days::new(52 * 7 + days_since_sunday);
+
+    let mut weeks: Vec<Week> = vec![];
+    while date <= today {
+        if date.weekday().num_days_from_sunday() == 0 {
+            weeks.push(Week {
+                contribution_days: vec![],
+            });
+        }
+
+        let key = date.format("%Y-%m-%d").to_string();
+        if let Some(week) = weeks.last_mut() {
+            week.contribution_days.push(ContributionDay {
+                contribution_count: counts.get(&key).copied().unwrap_or(0),
+                date: key,
+            });
+        }
+
+        date = date + Days::new(1);
+    }
+
+    Ok(ContributionCalendar { weeks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn forbidden_with_requests_left_is_not_a_rate_limit() {
+        let headers = headers(&[
+            ("x-ratelimit-remaining", "4999"),
+            ("x-ratelimit-reset", "1750000000"),
+        ]);
+
+        assert!(rate_limit(StatusCode::FORBIDDEN, &headers).is_none());
+    }
+
+    #[test]
+    fn forbidden_without_requests_left_waits_for_the_reset() {
+        let headers = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1750000000"),
+        ]);
+        let limit = rate_limit(StatusCode::FORBIDDEN, &headers).unwrap();
+
+        assert_eq!(limit.until.timestamp(), 1750000000);
+    }
+
+    #[test]
+    fn retry_after_takes_precedence() {
+        let headers = headers(&[("retry-after", "60"), ("x-ratelimit-reset", "1750000000")]);
+        let limit = rate_limit(StatusCode::FORBIDDEN, &headers).unwrap();
+
+        let wait = limit.until - Utc::now();
+        assert!(wait > TimeDelta::seconds(55) && wait <= TimeDelta::seconds(60));
+    }
+
+    #[test]
+    fn too_many_requests_uses_gitlab_headers() {
+        let headers = headers(&[
+            ("ratelimit-remaining", "0"),
+            ("ratelimit-reset", "1750000000"),
+        ]);
+        let limit = rate_limit(StatusCode::TOO_MANY_REQUESTS, &headers).unwrap();
+
+        assert_eq!(limit.until.timestamp(), 1750000000);
+    }
+
+    #[test]
+    fn other_statuses_are_not_rate_limits() {
+        let headers = headers(&[("x-ratelimit-remaining", "0"), ("retry-after", "60")]);
+
+        assert!(rate_limit(StatusCode::OK, &headers).is_none());
+        assert!(rate_limit(StatusCode::UNAUTHORIZED, &headers).is_none());
+    }
+}
@@ -1,7 +1,18 @@
 use crate::api::fetch_contributions;
-use crate::config::Config;
+use crate::cache::{self, Cache, CacheKey, CachedEntry, UpdateError};
+use crate::cells::CellMode;
+use crate::config::{Config, Profile, Provider};
+use crate::diff::{self, DayChange};
+use crate::input::LineEditor;
 use crate::models::ContributionCalendar;
-use anyhow::Result;
+use crate::scale::Scaling;
+use crate::stats;
+use crate::theme::Theme;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::time::{Duration, Instant};
+
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(5);
 
 #[derive(PartialEq)]
 pub enum Focus {
@@ -11,27 +22,334 @@ pub enum Focus {
 
 pub struct App {
     pub config: Config,
+    pub theme: Theme,
+    pub scaling: Scaling,
+    pub cells: CellMode,
+    /// The active profile, whose username may be overridden on the command line.
+    pub profile: Profile,
     pub focus: Focus,
+    pub username_input: LineEditor,
+    pub token_input: LineEditor,
+    /// Shows the token in clear text on the setup screen.
+    pub reveal_token: bool,
     pub calendar: Option<ContributionCalendar>,
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub watching: bool,
+    /// Never touch the network, only read from the cache.
+    pub offline: bool,
+    pub changes: Vec<DayChange>,
+    pub highlight_until: Option<Instant>,
+    /// A failed background refresh recorded in the cache, shown as a warning.
+    pub last_error: Option<UpdateError>,
+    /// Cache entries discarded on load, shown under the graph.
+    pub warnings: Vec<String>,
+    /// Day selected with the arrow keys in watch mode, hidden until one is pressed.
+    pub cursor: Option<NaiveDate>,
+    /// Weeks hidden to the right of the graph, 0 showing the latest.
+    pub scroll: usize,
 }
 
 impl App {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Config, profile: Profile) -> Self {
         Self {
+            username_input: LineEditor::new(&profile.username),
+            token_input: LineEditor::new(&profile.token),
             config,
+            theme: Theme::default(),
+            scaling: Scaling::default(),
+            cells: CellMode::default(),
+            profile,
             focus: Focus::Username,
+            reveal_token: false,
             calendar: None,
+            fetched_at: None,
+            watching: false,
+            offline: false,
+            changes: vec![],
+            highlight_until: None,
+            last_error: None,
+            warnings: vec![],
+            cursor: None,
+            scroll: 0,
         }
     }
 
     pub async fn load(&mut self) -> Result<()> {
-        let calendar = fetch_contributions(&self.config.token, &self.config.username).await?;
+        if self.offline {
+            let cache = Cache::load()?;
+            for warning in cache.warnings() {
+                if !self.warnings.contains(warning) {
+                    self.warnings.push(warning.clone());
+                }
+            }
+            let entry = cache.get(&self.cache_key());
+            let Some(entry) = entry.filter(|entry| entry.has_calendar()) else {
+                let failure = entry
+                    .and_then(|entry| entry.last_error.as_ref())
+                    .map(|error| format!(" (the last refresh failed: {})", error.message))
+                    .unwrap_or_default();
+                bail!(
+                    "@{} is not cached{}, run without --offline to fetch it",
+                    self.profile.username,
+                    failure
+                );
+            };
+            self.calendar = Some(entry.calendar.clone());
+            self.fetched_at = Some(entry.fetched_at);
+            self.last_error = entry.last_error.clone();
+            return Ok(());
+        }
+
+        // GitLab calendars of public profiles can be read anonymously.
+        let token = if self.profile.provider == Provider::GitLab && !self.profile.has_token() {
+            String::new()
+        } else {
+            self.profile.resolve_token()?
+        };
+        let calendar = fetch_contributions(&self.profile, &token, &self.config.network).await?;
         self.calendar = Some(calendar);
+        self.fetched_at = Some(Utc::now());
+        self.last_error = None;
         Ok(())
     }
 
-    pub async fn refresh(&mut self) -> Result<()> {
+    /// Fetches the user and stores the result in the cache and history.
+    pub async fn refresh_cache(&mut self) -> Result<CachedEntry> {
         self.load().await?;
-        Ok(())
+        let calendar = self.calendar.as_ref().context("Nothing was fetched")?;
+        cache::store(&self.cache_key(), calendar)
+    }
+
+    pub fn cache_key(&self) -> CacheKey {
+        CacheKey::new(self.profile.host(), &self.profile.username)
+    }
+
+    /// Reloads the graph in watch mode, storing fetches in the cache and
+    /// history, and highlights what changed since the graph on screen.
+    pub async fn refresh(&mut self) -> Result<()> {
+        let previous = self.calendar.take();
+        let result = if self.offline {
+            self.load().await
+        } else {
+            self.refresh_cache().await.map(|_| ())
+        };
+
+        match (previous, &self.calendar) {
+            (Some(previous), Some(current)) => {
+                let changes = diff::diff(&previous, current);
+                if !changes.is_empty() {
+                    self.set_changes(changes);
+                }
+            }
+            (previous, None) => self.calendar = previous,
+            _ => {}
+        }
+
+        result
+    }
+
+    pub fn load_cached(&mut self, entry: &CachedEntry) {
+        self.calendar = Some(entry.calendar.clone());
+        self.fetched_at = Some(entry.fetched_at);
+        self.last_error = entry.last_error.clone();
+        self.set_changes(entry.changes.clone());
+    }
+
+    pub fn set_changes(&mut self, changes: Vec<DayChange>) {
+        self.changes = changes;
+        if self.watching {
+            self.highlight_until = Some(Instant::now() + HIGHLIGHT_DURATION);
+        }
+    }
+
+    /// Changed cells are highlighted for a few seconds in watch mode and
+    /// always in single mode.
+    pub fn is_highlighting(&self) -> bool {
+        !self.changes.is_empty()
+            && self
+                .highlight_until
+                .is_none_or(|until| Instant::now() < until)
+    }
+
+    /// The setup screen skips the token when the profile reads it from
+    /// `token_command` or `token_file`.
+    pub fn asks_token(&self) -> bool {
+        self.profile.token_command.is_none() && self.profile.token_file.is_none()
+    }
+
+    /// GitLab calendars of public profiles can be read without a token.
+    pub fn requires_token(&self) -> bool {
+        self.asks_token() && self.profile.provider == Provider::GitHub
+    }
+
+    pub fn focused_input(&mut self) -> &mut LineEditor {
+        match self.focus {
+            Focus::Username => &mut self.username_input,
+            Focus::Token => &mut self.token_input,
+        }
+    }
+
+    pub fn is_changed(&self, date: &str) -> bool {
+        self.changes.iter().any(|change| change.date == date)
+    }
+
+    /// Moves the cursor by whole weeks and days within a week, stopping at
+    /// the edges, and scrolls to keep it among the `page` visible weeks. The
+    /// first move shows it on today.
+    pub fn move_cursor(&mut self, weeks: isize, days: isize, page: usize) -> bool {
+        let Some(calendar) = &self.calendar else {
+            return false;
+        };
+
+        let day = match self.cursor.and_then(|date| stats::find_day(calendar, date)) {
+            Some((week_idx, day_idx)) => {
+                let week_idx = week_idx
+                    .saturating_add_signed(weeks)
+                    .min(calendar.weeks.len().saturating_sub(1));
+                let week = &calendar.weeks[week_idx];
+                let day_idx = day_idx
+                    .saturating_add_signed(days)
+                    .min(week.contribution_days.len().saturating_sub(1));
+                week.contribution_days.get(day_idx)
+            }
+            None => stats::today(calendar).map(|(_, today)| today),
+        };
+        let Some(date) = day.and_then(|day| day.date.parse::<NaiveDate>().ok()) else {
+            return false;
+        };
+
+        self.cursor = Some(date);
+        self.follow_cursor(page);
+        true
+    }
+
+    /// Scrolls by `weeks`, negative towards older ones. The cursor, when
+    /// shown, moves along.
+    pub fn scroll(&mut self, weeks: isize, page: usize) -> bool {
+        if self.cursor.is_some() {
+            return self.move_cursor(weeks, 0, page);
+        }
+        let Some(calendar) = &self.calendar else {
+            return false;
+        };
+
+        let max_scroll = calendar.weeks.len().saturating_sub(page) as isize;
+        let scroll = (self.scroll as isize - weeks).clamp(0, max_scroll) as usize;
+        let scrolled = scroll != self.scroll;
+        self.scroll = scroll;
+        scrolled
+    }
+
+    /// Left and right: moves the cursor by `weeks` when it's shown or there's
+    /// nothing to scroll, scrolling once it passes the edge, and otherwise
+    /// scrolls.
+    pub fn step(&mut self, weeks: isize, page: usize) -> bool {
+        let fits = self
+            .calendar
+            .as_ref()
+            .is_some_and(|calendar| calendar.weeks.len() <= page);
+
+        if self.cursor.is_some() || fits {
+            self.move_cursor(weeks, 0, page)
+        } else {
+            self.scroll(weeks, page)
+        }
+    }
+
+    fn follow_cursor(&mut self, page: usize) {
+        let Some((week_idx, _)) = self
+            .calendar
+            .as_ref()
+            .zip(self.cursor)
+            .and_then(|(calendar, date)| stats::find_day(calendar, date))
+        else {
+            return;
+        };
+
+        let (start, end) = self.week_range(page);
+        if week_idx < start {
+            self.scroll += start - week_idx;
+        } else if week_idx >= end {
+            self.scroll -= week_idx + 1 - end;
+        }
+    }
+
+    /// Indices of the first and past-the-last week shown when `page` weeks fit.
+    pub fn week_range(&self, page: usize) -> (usize, usize) {
+        let weeks = self
+            .calendar
+            .as_ref()
+            .map_or(0, |calendar| calendar.weeks.len());
+        let page = page.min(weeks);
+        let end = weeks - self.scroll.min(weeks - page);
+        (end - page, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContributionDay, Week};
+
+    /// `weeks` full weeks, starting on Sunday 2024-01-07.
+    fn app(weeks: usize) -> App {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let mut app = App::new(Config::default(), Profile::default());
+        app.calendar = Some(ContributionCalendar {
+            weeks: (0..weeks)
+                .map(|week| Week {
+                    contribution_days: (0..7)
+                        .map(|day| ContributionDay {
+                            date: (start + chrono::Days::new((week * 7 + day) as u64))
+                                .format("%Y-%m-%d")
+                                .to_string(),
+                            contribution_count: 0,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        });
+        app
+    }
+
+    fn cursor_week(app: &App) -> Option<usize> {
+        let calendar = app.calendar.as_ref()?;
+        stats::find_day(calendar, app.cursor?).map(|(week, _)| week)
+    }
+
+    #[test]
+    fn step_scrolls_while_the_cursor_is_hidden() {
+        let mut app = app(10);
+
+        assert!(app.step(-1, 4));
+        assert_eq!(app.cursor, None);
+        assert_eq!(app.week_range(4), (5, 9));
+    }
+
+    #[test]
+    fn step_moves_the_cursor_and_scrolls_at_the_edge() {
+        let mut app = app(10);
+        app.move_cursor(0, 0, 4);
+        assert_eq!(cursor_week(&app), Some(9));
+
+        for _ in 0..3 {
+            assert!(app.step(-1, 4));
+        }
+        assert_eq!(cursor_week(&app), Some(6));
+        assert_eq!(app.week_range(4), (6, 10));
+
+        assert!(app.step(-1, 4));
+        assert_eq!(cursor_week(&app), Some(5));
+        assert_eq!(app.week_range(4), (5, 9));
+    }
+
+    #[test]
+    fn step_shows_the_cursor_when_the_year_fits() {
+        let mut app = app(10);
+
+        assert!(app.step(1, 10));
+        assert_eq!(cursor_week(&app), Some(9));
+        assert!(app.step(-1, 10));
+        assert_eq!(cursor_week(&app), Some(8));
     }
 }
@@ -1,10 +1,142 @@
-use anyhow::{Result, anyhow};
-use std::{env, process, process::Command};
+use crate::app::App;
+use crate::cache::{self, Cache, CacheKey};
+use crate::diff;
+use crate::paths;
+use crate::storage::FileLock;
+use anyhow::{Context, Result, anyhow};
+use chrono::{Local, TimeDelta, Utc};
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
 
+/// The updater log is rotated once it grows past this size.
+const LOG_MAX_BYTES: u64 = 1024 * 1024;
+/// Rotated logs kept next to it, as `updater.log.1` (newest) and so on.
+const LOG_BACKUPS: u32 = 3;
+
+pub fn log_path() -> Result<PathBuf> {
+    paths::cache_file("updater.log")
+}
+
+/// Appends a timestamped line to the updater log, which background updaters
+/// and the daemon share.
+pub fn log(message: &str) {
+    let line = format!(
+        "[{}] {}\n",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        message
+    );
+
+    if let Ok(mut file) = open_log() {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+fn rotated(path: &Path, generation: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", generation));
+    PathBuf::from(name)
+}
+
+fn open_log() -> Result<File> {
+    let path = log_path()?;
+
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > LOG_MAX_BYTES) {
+        for generation in (1..LOG_BACKUPS).rev() {
+            let _ = fs::rename(rotated(&path, generation), rotated(&path, generation + 1));
+        }
+        let _ = fs::rename(&path, rotated(&path, 1));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+fn lock_path(key: &CacheKey) -> Result<PathBuf> {
+    paths::cache_file(&format!("updater.{}.{}", key.host, key.login))
+}
+
+/// Taken by the `--update-cache` child for as long as it runs, so only one
+/// updater per user and host fetches at a time. `None` if another one holds it.
+pub fn lock_updater(key: &CacheKey) -> Result<Option<FileLock>> {
+    let mut lock = FileLock::try_exclusive(&lock_path(key)?)?;
+    if let Some(lock) = &mut lock {
+        lock.write_pid()?;
+    }
+    Ok(lock)
+}
+
+/// What the `--update-cache` child runs: refreshes the app's user unless
+/// another updater is already at it or just finished.
+pub async fn update_cache(mut app: App, min_update_age: TimeDelta) -> Result<()> {
+    let key = app.cache_key();
+
+    let Some(_lock) = lock_updater(&key)? else {
+        return Ok(());
+    };
+    // Another updater may have finished while this one was starting.
+    if Cache::load()?
+        .get(&key)
+        .is_some_and(|entry| Utc::now() - entry.fetched_at < min_update_age)
+    {
+        return Ok(());
+    }
+
+    match app.refresh_cache().await {
+        Ok(entry) => {
+            let summary = diff::summary(&entry.changes)
+                .map(|summary| format!(" ({})", summary))
+                .unwrap_or_default();
+            log(&format!("Refreshed @{}{}", key.login, summary));
+        }
+        Err(err) => {
+            log(&format!("Failed to refresh @{}: {:#}", key.login, err));
+            cache::record_error(&key, &err)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Starts a detached `--update-cache` child unless one is already running for `key`.
 #[cfg(unix)]
-pub fn spawn_cache_updater(username: &str) -> Result<()> {
+pub fn spawn_cache_updater(profile: &str, username: &str, key: &CacheKey) -> Result<()> {
+    if FileLock::try_exclusive(&lock_path(key)?)?.is_none() {
+        return Ok(());
+    }
+
     let exe = env::current_exe()?;
 
+    let mut command = Command::new(exe);
+    command.arg("--update-cache").arg("--profile").arg(profile);
+
+    let overrides = paths::overrides();
+    if let Some(config) = &overrides.config {
+        command.arg("--config").arg(config);
+    }
+    if let Some(cache_dir) = &overrides.cache_dir {
+        command.arg("--cache-dir").arg(cache_dir);
+    }
+
+    command.arg(username);
+
+    let log = open_log()?;
+    command
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
     unsafe {
         match libc::fork() {
             -1 => return Err(anyhow!("Fork failed")),
@@ -26,10 +158,7 @@ pub fn spawn_cache_updater(username: &str) -> Result<()> {
                         libc::dup(0);
                         libc::dup(0);
 
-                        let status = Command::new(exe)
-                            .arg("--update-cache")
-                            .arg(username)
-                            .status();
+                        let status = command.status();
 
                         match status {
                             Ok(_) => process::exit(0),
@@ -1,77 +1,875 @@
+use crate::api;
+use crate::db::{Db, StoredEntry};
+use crate::diff::{self, DayChange};
+use crate::duration;
+use crate::history;
 use crate::models::ContributionCalendar;
+use crate::paths;
+use crate::storage;
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, env, fs, path::PathBuf};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
 
-#[derive(Serialize, Deserialize)]
+/// Identifies what was fetched: whose graph, from which host, over which
+/// date range and with which filter, so a narrower fetch never replaces the
+/// rolling year.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub host: String,
+    pub login: String,
+    pub range: String,
+    pub filter: String,
+}
+
+impl CacheKey {
+    /// The rolling year of all contributions, as shown by default.
+    pub fn new(host: &str, login: &str) -> Self {
+        Self {
+            host: host.to_lowercase(),
+            login: login.trim().to_lowercase(),
+            range: "year".to_string(),
+            filter: "all".to_string(),
+        }
+    }
+
+    pub fn parse(key: &str) -> Option<Self> {
+        let mut parts = key.split('/');
+        let key = Self {
+            host: parts.next()?.to_string(),
+            login: parts.next()?.to_string(),
+            range: parts.next()?.to_string(),
+            filter: parts.next()?.to_string(),
+        };
+        let complete = [&key.host, &key.login, &key.range, &key.filter]
+            .iter()
+            .all(|part| !part.is_empty());
+
+        (complete && parts.next().is_none()).then_some(key)
+    }
+
+    /// Whether this is the rolling year of all contributions.
+    pub fn is_default_scope(&self) -> bool {
+        self.range == "year" && self.filter == "all"
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            self.host, self.login, self.range, self.filter
+        )
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CachedEntry {
     pub calendar: ContributionCalendar,
     pub fetched_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<DayChange>,
+    /// Why the latest background refresh failed, cleared by the next success.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<UpdateError>,
+}
+
+impl CachedEntry {
+    /// Holds only the error of a refresh that failed before anything was
+    /// cached, so it can still be shown.
+    fn placeholder() -> Self {
+        Self {
+            calendar: ContributionCalendar::default(),
+            fetched_at: DateTime::UNIX_EPOCH,
+            changes: vec![],
+            last_error: None,
+        }
+    }
+
+    /// False for placeholders, since every fetched calendar has weeks.
+    pub fn has_calendar(&self) -> bool {
+        !self.calendar.weeks.is_empty()
+    }
 }
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UpdateError {
+    pub message: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Bumped whenever the layout of an entry changes; older entries are migrated on load.
+const SCHEMA_VERSION: u32 = 3;
+
+/// The layout of `cache export`, and of the `cache.json` used before the
+/// cache moved into the database.
+#[derive(Serialize)]
 struct CacheData {
+    pub version: u32,
     pub entries: HashMap<String, CachedEntry>,
 }
 
+impl Default for CacheData {
+    fn default() -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+/// Cached graphs, stored one row per key in the history database.
 pub struct Cache {
     data: CacheData,
+    /// Keys inserted or removed since loading, written back by [`Cache::update`].
+    changed: HashSet<String>,
+    /// What was discarded while loading, for the caller to show.
+    warnings: Vec<String>,
 }
 
 impl Cache {
-    fn path() -> Result<PathBuf> {
-        let home = env::var("HOME").context("HOME environment variable not set")?;
+    fn empty() -> Self {
+        Self {
+            data: CacheData::default(),
+            changed: HashSet::new(),
+            warnings: vec![],
+        }
+    }
 
-        Ok(PathBuf::from(home).join(".trexanh").join("cache.json"))
+    fn legacy_path() -> Result<PathBuf> {
+        paths::cache_file("cache.json")
+    }
+
+    fn open_db() -> Result<(Db, Vec<String>)> {
+        let mut db = Db::open()?;
+        let warnings = Self::import_legacy_file(&mut db)?;
+        Ok((db, warnings))
     }
 
     pub fn load() -> Result<Self> {
-        let path = Self::path()?;
+        let (db, warnings) = Self::open_db()?;
+        let mut cache = Self::read(&db)?;
+        cache.warnings.splice(0..0, warnings);
+        Ok(cache)
+    }
 
-        if !path.exists() {
-            return Ok(Self {
-                data: CacheData::default(),
-            });
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// For commands that don't draw a graph to show the warnings under.
+    pub fn print_warnings(&self) {
+        for warning in &self.warnings {
+            eprintln!("Warning: {}", warning);
         }
+    }
 
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read cache at {}", path.display()))?;
+    /// Reads every row. Rows that can't be migrated or deserialized are
+    /// skipped with a warning, and rows under an outdated key are moved to
+    /// the current one on the next update.
+    fn read(db: &Db) -> Result<Self> {
+        let mut cache = Self::empty();
 
-        let data: CacheData = serde_json::from_str(&content)
-            .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+        for row in db.entries()? {
+            let result = serde_json::from_str(&row.entry)
+                .context("Invalid JSON")
+                .and_then(|entry| cache.add(row.version, &row.key, entry));
 
-        Ok(Self { data })
+            match result {
+                Ok(key) if key != row.key => {
+                    cache.changed.insert(row.key);
+                    cache.changed.insert(key);
+                }
+                Ok(_) => {}
+                Err(err) => cache
+                    .warnings
+                    .push(format!("Discarded cached entry {}: {:#}", row.key, err)),
+            }
+        }
+
+        Ok(cache)
     }
 
-    pub fn save(&self) -> Result<()> {
-        let path = Self::path()?;
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    /// Parses a cache export of any schema version. Entries that can't be
+    /// migrated or deserialized are dropped with a warning instead of
+    /// failing the whole cache.
+    fn parse(content: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(content).context("Invalid JSON")?;
+
+        let version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        let Some(Value::Object(entries)) = value.get("entries").cloned() else {
+            anyhow::bail!("Missing entries");
+        };
+
+        let mut cache = Self::empty();
+
+        for (key, entry) in entries {
+            if let Err(err) = cache.add(version, &key, entry) {
+                cache
+                    .warnings
+                    .push(format!("Discarded cached entry {}: {:#}", key, err));
+            }
         }
 
-        let json = serde_json::to_string_pretty(&self.data)?;
-        fs::write(&path, json)
-            .with_context(|| format!("Failed to write cache to {}", path.display()))?;
+        Ok(cache)
+    }
 
-        Ok(())
+    /// Migrates and adds one entry, returning its current key.
+    fn add(&mut self, version: u32, key: &str, entry: Value) -> Result<String> {
+        let (key, entry) = migrate_entry(version, key, entry).context("unknown key format")?;
+        let entry = serde_json::from_value(entry)?;
+        let key_string = key.to_string();
+
+        self.insert_entry(key, entry);
+        Ok(key_string)
     }
 
-    pub fn get(&self, username: &str) -> Option<ContributionCalendar> {
-        self.data
+    fn insert_entry(&mut self, key: CacheKey, entry: CachedEntry) -> bool {
+        let key = key.to_string();
+        let newer = self
+            .data
             .entries
-            .get(username)
-            .map(|entry| entry.calendar.clone())
+            .get(&key)
+            .is_none_or(|current| current.fetched_at < entry.fetched_at);
+
+        if newer {
+            self.data.entries.insert(key, entry);
+        }
+
+        newer
     }
 
-    pub fn insert(&mut self, username: String, calendar: ContributionCalendar) {
+    /// Writes the rows of changed keys, leaving every other row alone.
+    fn save(&self, db: &Db) -> Result<()> {
+        for key in &self.changed {
+            match self.data.entries.get(key) {
+                Some(entry) => db.put_entry(&StoredEntry {
+                    key: key.clone(),
+                    version: SCHEMA_VERSION,
+                    entry: serde_json::to_string(entry)?,
+                })?,
+                None => db.delete_entry(key)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a read-modify-write cycle in one transaction, so concurrent
+    /// trexanh processes don't lose each other's entries.
+    pub fn update<T>(f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let (mut db, warnings) = Self::open_db()?;
+
+        db.immediate(|db| {
+            Self::update_in(db, |cache| {
+                cache.warnings.splice(0..0, warnings);
+                f(cache)
+            })
+        })
+    }
+
+    fn update_in<T>(db: &Db, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let mut cache = Self::read(db)?;
+        let result = f(&mut cache)?;
+        cache.save(db)?;
+
+        Ok(result)
+    }
+
+    /// Moves the entries of the `cache.json` used by earlier versions into
+    /// the database, then renames the file so it is only imported once. A
+    /// file that can't be parsed at all is moved to `cache.json.bak` instead.
+    fn import_legacy_file(db: &mut Db) -> Result<Vec<String>> {
+        let path = Self::legacy_path()?;
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read cache at {}", path.display()))?;
+
+        let (renamed, warnings) = match Self::parse(&content) {
+            Ok(mut imported) => {
+                let warnings = std::mem::take(&mut imported.warnings);
+                db.immediate(|db| Self::update_in(db, |cache| Ok(cache.merge(imported))))?;
+                (path.with_extension("json.imported"), warnings)
+            }
+            Err(err) => {
+                let backup = path.with_extension("json.bak");
+                let warning = format!("Moved unreadable cache to {}: {:#}", backup.display(), err);
+                (backup, vec![warning])
+            }
+        };
+
+        // Another process may have imported and renamed it in the meantime.
+        match fs::rename(&path, &renamed) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Failed to rename {}", path.display()))
+            }
+            _ => Ok(warnings),
+        }
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<&CachedEntry> {
+        self.data.entries.get(&key.to_string())
+    }
+
+    /// Replaces the cached calendar and returns what changed since the previous fetch.
+    pub fn insert(&mut self, key: &CacheKey, calendar: ContributionCalendar) -> Vec<DayChange> {
+        let changes = self
+            .get(key)
+            .filter(|previous| previous.has_calendar())
+            .map(|previous| diff::diff(&previous.calendar, &calendar))
+            .unwrap_or_default();
+
         self.data.entries.insert(
-            username,
+            key.to_string(),
             CachedEntry {
                 calendar,
                 fetched_at: Utc::now(),
+                changes: changes.clone(),
+                last_error: None,
             },
         );
+        self.changed.insert(key.to_string());
+
+        changes
+    }
+
+    /// Records a failed refresh. An existing entry keeps its calendar,
+    /// otherwise a placeholder holds the error.
+    pub fn set_error(&mut self, key: &CacheKey, message: String) {
+        let entry = self
+            .data
+            .entries
+            .entry(key.to_string())
+            .or_insert_with(CachedEntry::placeholder);
+
+        entry.last_error = Some(UpdateError {
+            message,
+            at: Utc::now(),
+        });
+        self.changed.insert(key.to_string());
+    }
+
+    /// Drops entries fetched longer than `max_age` ago and returns how many were removed.
+    pub fn prune(&mut self, max_age: TimeDelta) -> usize {
+        let now = Utc::now();
+        let stale: Vec<String> = self
+            .data
+            .entries
+            .iter()
+            .filter(|(_, entry)| now - entry.fetched_at > max_age)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in &stale {
+            self.data.entries.remove(key);
+        }
+        let removed = stale.len();
+        self.changed.extend(stale);
+
+        removed
+    }
+
+    pub fn remove(&mut self, key: &CacheKey) -> bool {
+        let key = key.to_string();
+        let removed = self.data.entries.remove(&key).is_some();
+        self.changed.insert(key);
+        removed
+    }
+
+    pub fn clear(&mut self) -> usize {
+        let count = self.data.entries.len();
+        self.changed
+            .extend(self.data.entries.drain().map(|(key, _)| key));
+        count
+    }
+
+    /// Merges entries from another cache, keeping whichever copy was fetched last.
+    pub fn merge(&mut self, other: Cache) -> usize {
+        let mut merged = 0;
+
+        for (key, entry) in other.data.entries {
+            if let Some(key) = CacheKey::parse(&key)
+                && self.insert_entry(key.clone(), entry)
+            {
+                self.changed.insert(key.to_string());
+                merged += 1;
+            }
+        }
+
+        merged
+    }
+}
+
+/// Caches a fetched calendar and records the days that changed in the
+/// history, returning the stored entry.
+pub fn store(key: &CacheKey, calendar: &ContributionCalendar) -> Result<CachedEntry> {
+    let (entry, record) = Cache::update(|cache| {
+        let baseline = cache.get(key).is_none_or(|entry| !entry.has_calendar());
+        let changes = cache.insert(key, calendar.clone());
+
+        let logged = if baseline {
+            diff::diff(&ContributionCalendar::default(), calendar)
+        } else {
+            changes
+        };
+
+        let entry = cache.get(key).cloned().context("Stored graph is missing")?;
+        let record = history::Record {
+            username: key.login.clone(),
+            host: key.host.clone(),
+            fetched_at: entry.fetched_at,
+            baseline,
+            changes: logged,
+        };
+
+        Ok((entry, record))
+    })?;
+
+    // Written after the cache's transaction, which holds the database's write lock.
+    if !record.changes.is_empty() {
+        history::append(&record)?;
+    }
+
+    Ok(entry)
+}
+
+/// Remembers a failed refresh on the cached entry so the next foreground run can show it.
+pub fn record_error(key: &CacheKey, err: &anyhow::Error) -> Result<()> {
+    Cache::update(|cache| {
+        cache.set_error(key, format!("{:#}", err));
+        Ok(())
+    })
+}
+
+/// Brings an entry written by an older schema version up to date.
+fn migrate_entry(version: u32, key: &str, entry: Value) -> Option<(CacheKey, Value)> {
+    let key = match version {
+        // Version 0 was keyed by the raw username.
+        0 => CacheKey::parse(key).unwrap_or_else(|| CacheKey::new(api::GITHUB_HOST, key)),
+        // Version 2 left out the range and filter, it only cached the rolling year.
+        2 => CacheKey::parse(&format!("{}/year/all", key))?,
+        _ => CacheKey::parse(key)?,
+    };
+
+    Some((key, entry))
+}
+
+pub fn print_list() -> Result<()> {
+    let cache = Cache::load()?;
+    cache.print_warnings();
+
+    if cache.data.entries.is_empty() {
+        println!("Cache is empty");
+        return Ok(());
+    }
+
+    let mut entries: Vec<_> = cache.data.entries.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let name_width = entries
+        .iter()
+        .map(|(key, _)| key.len())
+        .max()
+        .unwrap_or(0)
+        .max("KEY".len());
+
+    println!(
+        "{:<name_width$}  {:<16}  {:>8}  {:>8}",
+        "KEY", "FETCHED", "AGE", "SIZE"
+    );
+
+    for (key, entry) in entries {
+        let size = serde_json::to_string(entry)
+            .map(|json| json.len())
+            .unwrap_or(0);
+
+        let (fetched, age) = if entry.has_calendar() {
+            (
+                entry
+                    .fetched_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                duration::format_age(entry.fetched_at),
+            )
+        } else {
+            ("never (failed)".to_string(), "-".to_string())
+        };
+
+        println!(
+            "{:<name_width$}  {:<16}  {:>8}  {:>8}",
+            key,
+            fetched,
+            age,
+            format_size(size)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn print_entry(key: &CacheKey) -> Result<()> {
+    let cache = Cache::load()?;
+    cache.print_warnings();
+    let entry = cache
+        .get(key)
+        .with_context(|| format!("@{} is not cached", key.login))?;
+
+    let days: Vec<_> = entry
+        .calendar
+        .weeks
+        .iter()
+        .flat_map(|week| week.contribution_days.iter())
+        .collect();
+    let total: u32 = days.iter().map(|day| day.contribution_count).sum();
+
+    println!("User:          @{}", key.login);
+    println!("Host:          {}", key.host);
+    println!("Scope:         {} / {}", key.range, key.filter);
+    if entry.has_calendar() {
+        println!(
+            "Fetched:       {} ({})",
+            entry
+                .fetched_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+            duration::format_age(entry.fetched_at)
+        );
+    } else {
+        println!("Fetched:       never");
+    }
+    if let (Some(first), Some(last)) = (days.first(), days.last()) {
+        println!("Range:         {} to {}", first.date, last.date);
+    }
+    println!("Days:          {}", days.len());
+    println!("Contributions: {}", total);
+    if let Some(summary) = diff::summary(&entry.changes) {
+        println!("Last changes:  {}", summary);
+    }
+    if let Some(error) = &entry.last_error {
+        println!(
+            "Last error:    {} ({})",
+            error.message,
+            duration::format_age(error.at)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn export(path: Option<&Path>) -> Result<()> {
+    let cache = Cache::load()?;
+    cache.print_warnings();
+    let json = serde_json::to_string_pretty(&cache.data)?;
+
+    match path {
+        Some(path) => storage::write_atomic(path, &json),
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    }
+}
+
+pub fn import(path: Option<&Path>) -> Result<usize> {
+    let content = match path {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?,
+        None => {
+            let mut content = String::new();
+            io::stdin()
+                .read_to_string(&mut content)
+                .context("Failed to read cache from stdin")?;
+            content
+        }
+    };
+
+    let imported = Cache::parse(&content).context("Invalid imported cache")?;
+    imported.print_warnings();
+
+    Cache::update(|cache| {
+        cache.print_warnings();
+        Ok(cache.merge(imported))
+    })
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(fetched_at: &str) -> Value {
+        json!({ "calendar": { "weeks": [] }, "fetched_at": fetched_at })
+    }
+
+    fn stored(key: &str, version: u32, entry: Value) -> StoredEntry {
+        StoredEntry {
+            key: key.to_string(),
+            version,
+            entry: entry.to_string(),
+        }
+    }
+
+    fn keys(cache: &Cache) -> Vec<String> {
+        let mut keys: Vec<_> = cache.data.entries.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn keys_fold_case_and_whitespace() {
+        let key = CacheKey::new("GitHub.com", " Alice ");
+
+        assert_eq!(key.to_string(), "github.com/alice/year/all");
+        assert!(key == CacheKey::new("github.com", "alice"));
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        let key = CacheKey::new("gitlab.example.com", "bob");
+
+        assert!(CacheKey::parse(&key.to_string()) == Some(key));
+        assert!(CacheKey::parse("github.com/alice/2024/org:acme").is_some());
+        assert!(CacheKey::parse("github.com/alice").is_none());
+        assert!(CacheKey::parse("github.com/alice/year/").is_none());
+        assert!(CacheKey::parse("/alice/year/all").is_none());
+        assert!(CacheKey::parse("github.com/alice/year/all/more").is_none());
+    }
+
+    #[test]
+    fn migrates_usernames_of_version_0() {
+        let content = json!({
+            "entries": {
+                "Alice": entry("2025-01-01T00:00:00Z"),
+                "alice": entry("2025-02-01T00:00:00Z"),
+                "bob": entry("2025-01-01T00:00:00Z"),
+            }
+        });
+        let cache = Cache::parse(&content.to_string()).unwrap();
+
+        assert_eq!(
+            keys(&cache),
+            ["github.com/alice/year/all", "github.com/bob/year/all"]
+        );
+        let alice = cache.get(&CacheKey::new("github.com", "alice")).unwrap();
+        assert_eq!(alice.fetched_at.to_rfc3339(), "2025-02-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn keeps_scoped_keys_of_version_1() {
+        let content = json!({
+            "version": 1,
+            "entries": {
+                "github.com/alice/year/all": entry("2025-01-01T00:00:00Z"),
+                "github.com/alice/month/all": entry("2025-01-01T00:00:00Z"),
+                "gitlab.com/bob/year/all": entry("2025-01-01T00:00:00Z"),
+            }
+        });
+        let cache = Cache::parse(&content.to_string()).unwrap();
+
+        assert_eq!(
+            keys(&cache),
+            [
+                "github.com/alice/month/all",
+                "github.com/alice/year/all",
+                "gitlab.com/bob/year/all"
+            ]
+        );
+    }
+
+    #[test]
+    fn migrates_unscoped_keys_of_version_2() {
+        let content = json!({
+            "version": 2,
+            "entries": {
+                "github.com/alice": entry("2025-01-01T00:00:00Z"),
+                "github.com/alice/year/all": entry("2025-01-01T00:00:00Z"),
+            }
+        });
+        let cache = Cache::parse(&content.to_string()).unwrap();
+
+        assert_eq!(keys(&cache), ["github.com/alice/year/all"]);
+        assert_eq!(cache.warnings().len(), 1);
+    }
+
+    #[test]
+    fn updates_only_write_changed_rows() {
+        let db = Db::open_in_memory().unwrap();
+        db.put_entry(&stored(
+            "github.com/alice/year/all",
+            3,
+            entry("2025-01-01T00:00:00Z"),
+        ))
+        .unwrap();
+        db.put_entry(&stored(
+            "github.com/bob/year/all",
+            3,
+            entry("2025-01-01T00:00:00Z"),
+        ))
+        .unwrap();
+
+        Cache::update_in(&db, |cache| {
+            cache.insert(&CacheKey::new("github.com", "alice"), Default::default());
+            Ok(())
+        })
+        .unwrap();
+
+        let rows = db.entries().unwrap();
+        assert_ne!(rows[0].entry, entry("2025-01-01T00:00:00Z").to_string());
+        assert_eq!(rows[1].entry, entry("2025-01-01T00:00:00Z").to_string());
+    }
+
+    #[test]
+    fn rows_under_outdated_keys_move_on_update() {
+        let db = Db::open_in_memory().unwrap();
+        db.put_entry(&stored(
+            "github.com/alice",
+            2,
+            entry("2025-01-01T00:00:00Z"),
+        ))
+        .unwrap();
+        db.put_entry(&stored(
+            "github.com/bob/year/all",
+            1,
+            entry("2025-01-01T00:00:00Z"),
+        ))
+        .unwrap();
+
+        Cache::update_in(&db, |_| Ok(())).unwrap();
+
+        let keys: Vec<_> = db
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|row| row.key)
+            .collect();
+        assert_eq!(
+            keys,
+            ["github.com/alice/year/all", "github.com/bob/year/all"]
+        );
+    }
+
+    #[test]
+    fn discards_bad_entries_next_to_good_ones() {
+        let content = json!({
+            "version": 3,
+            "entries": {
+                "github.com/alice/year/all": entry("2025-01-01T00:00:00Z"),
+                "github.com/bob/year/all": { "calendar": "not a calendar" },
+                "not a key": entry("2025-01-01T00:00:00Z"),
+                "github.com/carol/year/all": entry("2025-01-01T00:00:00Z"),
+            }
+        });
+        let cache = Cache::parse(&content.to_string()).unwrap();
+
+        assert_eq!(
+            keys(&cache),
+            ["github.com/alice/year/all", "github.com/carol/year/all"]
+        );
+        let mut warnings = cache.warnings().to_vec();
+        warnings.sort();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("Discarded cached entry github.com/bob/year/all: "));
+        assert_eq!(
+            warnings[1],
+            "Discarded cached entry not a key: unknown key format"
+        );
+    }
+
+    #[test]
+    fn rejects_files_without_entries() {
+        assert!(Cache::parse("not json").is_err());
+        assert!(Cache::parse("{\"version\": 2}").is_err());
+    }
+
+    #[test]
+    fn skips_unreadable_rows() {
+        let db = Db::open_in_memory().unwrap();
+        db.put_entry(&stored(
+            "github.com/alice/year/all",
+            3,
+            entry("2025-01-01T00:00:00Z"),
+        ))
+        .unwrap();
+        db.put_entry(&StoredEntry {
+            key: "github.com/bob/year/all".to_string(),
+            version: 3,
+            entry: "{".to_string(),
+        })
+        .unwrap();
+
+        let cache = Cache::read(&db).unwrap();
+
+        assert_eq!(keys(&cache), ["github.com/alice/year/all"]);
+        assert_eq!(cache.warnings().len(), 1);
+        assert_eq!(db.entries().unwrap().len(), 2);
+    }
+
+    fn calendar(counts: &[u32]) -> ContributionCalendar {
+        serde_json::from_value(json!({
+            "weeks": [{
+                "contributionDays": counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, count)| json!({
+                        "date": format!("2025-01-0{}", i + 1),
+                        "contributionCount": count,
+                    }))
+                    .collect::<Vec<_>>(),
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn errors_without_an_entry_leave_a_placeholder() {
+        let key = CacheKey::new("github.com", "alice");
+        let mut cache = Cache::empty();
+
+        cache.set_error(&key, "Bad credentials".to_string());
+
+        let entry = cache.get(&key).unwrap();
+        assert!(!entry.has_calendar());
+        assert_eq!(
+            entry.last_error.as_ref().unwrap().message,
+            "Bad credentials"
+        );
+
+        let changes = cache.insert(&key, calendar(&[0, 3]));
+        assert!(changes.is_empty());
+        assert!(cache.get(&key).unwrap().last_error.is_none());
+    }
+
+    #[test]
+    fn errors_keep_the_cached_calendar() {
+        let key = CacheKey::new("github.com", "alice");
+        let mut cache = Cache::empty();
+        cache.insert(&key, calendar(&[1, 2]));
+
+        cache.set_error(&key, "Bad credentials".to_string());
+
+        let entry = cache.get(&key).unwrap();
+        assert!(entry.has_calendar());
+        assert!(entry.last_error.is_some());
     }
 }
@@ -0,0 +1,102 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// How days are drawn. The compact modes fit a whole year in about 60
+/// columns, or 30 with braille.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CellMode {
+    /// The widest mode that fits the whole year
+    #[default]
+    Auto,
+    /// `display.week_width` columns per week
+    Full,
+    /// One `■` per day, one column per week
+    Square,
+    /// Two days per character, one column per week
+    Half,
+    /// Braille dots for days with contributions, two weeks per column
+    Braille,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Cells {
+    Full { week_width: usize },
+    Square,
+    Half,
+    Braille,
+}
+
+impl Cells {
+    /// Picks the cells for `mode`, falling back to denser ones in auto mode
+    /// until `weeks` fit in `columns`.
+    pub fn resolve(mode: CellMode, week_width: usize, weeks: usize, columns: usize) -> Self {
+        let full = Self::Full { week_width };
+
+        match mode {
+            CellMode::Full => full,
+            CellMode::Square => Self::Square,
+            CellMode::Half => Self::Half,
+            CellMode::Braille => Self::Braille,
+            CellMode::Auto => [full, Self::Half]
+                .into_iter()
+                .find(|cells| cells.weeks_fitting(columns) >= weeks)
+                .unwrap_or(Self::Braille),
+        }
+    }
+
+    pub fn weeks_fitting(&self, columns: usize) -> usize {
+        match self {
+            Self::Full { week_width } => columns / week_width,
+            Self::Square | Self::Half => columns,
+            Self::Braille => columns * 2,
+        }
+    }
+
+    pub fn width(&self, weeks: usize) -> usize {
+        match self {
+            Self::Full { week_width } => weeks * week_width,
+            Self::Square | Self::Half => weeks,
+            Self::Braille => weeks.div_ceil(2),
+        }
+    }
+
+    /// Column of the week at `week_idx`.
+    pub fn column(&self, week_idx: usize) -> usize {
+        match self {
+            Self::Full { week_width } => week_idx * week_width,
+            Self::Square | Self::Half => week_idx,
+            Self::Braille => week_idx / 2,
+        }
+    }
+
+    /// Days of the week drawn on each row.
+    pub fn days_per_row(&self) -> usize {
+        match self {
+            Self::Full { .. } | Self::Square => 1,
+            Self::Half => 2,
+            Self::Braille => 4,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        7usize.div_ceil(self.days_per_row())
+    }
+
+    pub fn row_label(&self, row: usize) -> Option<&'static str> {
+        let labels: &[Option<&str>] = match self {
+            Self::Full { .. } | Self::Square => &[
+                None,
+                Some("Mon"),
+                None,
+                Some("Wed"),
+                None,
+                Some("Fri"),
+                None,
+            ],
+            Self::Half => &[Some("Mon"), Some("Wed"), Some("Fri"), None],
+            Self::Braille => &[Some("Mon"), Some("Fri")],
+        };
+        labels.get(row).copied().flatten()
+    }
+}
@@ -0,0 +1,291 @@
+use clap::ValueEnum;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The colors a terminal can show, from none to 24-bit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Support {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl Support {
+    pub fn detect(choice: ColorChoice) -> Self {
+        match choice {
+            ColorChoice::Never => Self::None,
+            ColorChoice::Always => depth(),
+            ColorChoice::Auto => {
+                let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+
+                if no_color || dumb || !io::stdout().is_terminal() {
+                    Self::None
+                } else {
+                    depth()
+                }
+            }
+        }
+    }
+
+    /// The closest color this terminal can show, or `Color::Reset` without color.
+    pub fn adapt(self, color: Color) -> Color {
+        let Color::Rgb(r, g, b) = color else {
+            return if self == Self::None {
+                Color::Reset
+            } else {
+                color
+            };
+        };
+
+        match self {
+            Self::None => Color::Reset,
+            Self::Ansi16 => nearest_ansi16((r, g, b)),
+            Self::Ansi256 => Color::Indexed(nearest_ansi256((r, g, b))),
+            Self::TrueColor => color,
+        }
+    }
+}
+
+/// Guesses the color depth from the environment, since terminals can't be
+/// asked directly.
+fn depth() -> Support {
+    let colorterm = env::var("COLORTERM").unwrap_or_default();
+    let term = env::var("TERM").unwrap_or_default();
+
+    if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+        Support::TrueColor
+    } else if term.contains("256color") {
+        Support::Ansi256
+    } else {
+        Support::Ansi16
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// xterm's default values for the 16 basic colors.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, candidate)| distance(rgb, *candidate))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+/// The dark and bright variant of the six hues of the basic colors.
+const HUES: [(f64, Color, Color); 6] = [
+    (0.0, Color::Red, Color::LightRed),
+    (60.0, Color::Yellow, Color::LightYellow),
+    (120.0, Color::Green, Color::LightGreen),
+    (180.0, Color::Cyan, Color::LightCyan),
+    (240.0, Color::Blue, Color::LightBlue),
+    (300.0, Color::Magenta, Color::LightMagenta),
+];
+
+/// Hue in degrees and chroma of a color.
+fn hue((r, g, b): (u8, u8, u8)) -> (f64, f64) {
+    let (r, g, b) = (r as f64, g as f64, b as f64);
+    let max = r.max(g).max(b);
+    let chroma = max - r.min(g).min(b);
+
+    let sector = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        (g - b) / chroma
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+
+    ((sector * 60.0).rem_euclid(360.0), chroma)
+}
+
+/// Five distinct, ordered levels from the 16 basic colors: dark gray, then
+/// the dark and the bright variant of the palette's hue, each plain and
+/// bold. Matching the levels one by one would map most palettes to a single
+/// gray, since the basic colors only have two shades of each hue.
+pub fn ansi16_levels(levels: &[Color; 5]) -> [(Color, bool); 5] {
+    // Average the hues as angles, weighted by how colorful each level is.
+    let (x, y) = levels
+        .iter()
+        .filter_map(|color| match *color {
+            Color::Rgb(r, g, b) => Some(hue((r, g, b))),
+            _ => None,
+        })
+        .fold((0.0, 0.0), |(x, y), (hue, chroma)| {
+            let angle = hue.to_radians();
+            (x + chroma * angle.cos(), y + chroma * angle.sin())
+        });
+
+    let (dark, bright) = if x.hypot(y) < 1.0 {
+        (Color::Green, Color::LightGreen)
+    } else {
+        let hue = y.atan2(x).to_degrees().rem_euclid(360.0);
+        let off = |center: f64| {
+            let d = (center - hue).abs();
+            d.min(360.0 - d)
+        };
+        HUES.iter()
+            .min_by(|a, b| off(a.0).total_cmp(&off(b.0)))
+            .map_or((Color::Green, Color::LightGreen), |&(_, dark, bright)| {
+                (dark, bright)
+            })
+    };
+
+    [
+        (Color::DarkGray, false),
+        (dark, false),
+        (dark, true),
+        (bright, false),
+        (bright, true),
+    ]
+}
+
+/// Channel values of the 6x6x6 cube in colors 16-231.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Picks the closer of the nearest cube color and the nearest of the 24
+/// grays in 232-255. The first 16 are skipped since terminals redefine them.
+fn nearest_ansi256((r, g, b): (u8, u8, u8)) -> u8 {
+    let nearest_level = |value: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| (CUBE[i] as i32 - value as i32).abs())
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (CUBE[ri], CUBE[gi], CUBE[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_value = 8 + 10 * gray_index;
+    let gray = (gray_value, gray_value, gray_value);
+
+    if distance((r, g, b), gray) < distance((r, g, b), cube) {
+        232 + gray_index
+    } else {
+        (16 + 36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+/// The SGR parameters that set `color` as the foreground, `None` for the
+/// terminal's default.
+pub fn foreground(color: Color) -> Option<String> {
+    sgr(color, 30)
+}
+
+pub fn background(color: Color) -> Option<String> {
+    sgr(color, 40)
+}
+
+/// `base` is 30 for the foreground and 40 for the background.
+fn sgr(color: Color, base: u8) -> Option<String> {
+    let offset = match color {
+        Color::Rgb(r, g, b) => return Some(format!("{};2;{};{};{}", base + 8, r, g, b)),
+        Color::Indexed(index) => return Some(format!("{};5;{}", base + 8, index)),
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 60,
+        Color::LightRed => 61,
+        Color::LightGreen => 62,
+        Color::LightYellow => 63,
+        Color::LightBlue => 64,
+        Color::LightMagenta => 65,
+        Color::LightCyan => 66,
+        Color::White => 67,
+        Color::Reset => return None,
+    };
+
+    Some((base + offset).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(hex: u32) -> Color {
+        Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    }
+
+    #[test]
+    fn ansi16_levels_are_distinct_and_ordered() {
+        let greens = [0x373737, 0x5a8c78, 0x78b482, 0xa0d296, 0xdcf0aa].map(rgb);
+
+        assert_eq!(
+            ansi16_levels(&greens),
+            [
+                (Color::DarkGray, false),
+                (Color::Green, false),
+                (Color::Green, true),
+                (Color::LightGreen, false),
+                (Color::LightGreen, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn ansi16_levels_follow_the_palette_hue() {
+        let oranges = [0x161b22, 0x631c03, 0xbd561d, 0xfa7a18, 0xfddf68].map(rgb);
+        let grays = [0x000000, 0x444444, 0x888888, 0xbbbbbb, 0xffffff].map(rgb);
+
+        assert_eq!(ansi16_levels(&oranges)[4], (Color::LightRed, true));
+        assert_eq!(ansi16_levels(&grays)[1], (Color::Green, false));
+    }
+
+    #[test]
+    fn nearest_ansi256_prefers_grays_for_grays() {
+        assert_eq!(nearest_ansi256((0x37, 0x37, 0x37)), 237);
+        assert_eq!(nearest_ansi256((0, 255, 0)), 46);
+    }
+
+    #[test]
+    fn sgr_codes() {
+        assert_eq!(foreground(Color::DarkGray).as_deref(), Some("90"));
+        assert_eq!(background(Color::Green).as_deref(), Some("42"));
+        assert_eq!(foreground(Color::Indexed(236)).as_deref(), Some("38;5;236"));
+        assert_eq!(foreground(Color::Reset), None);
+    }
+}
@@ -1,18 +1,411 @@
-use anyhow::{Context, Result};
+use crate::api;
+use crate::cells::CellMode;
+use crate::color::ColorChoice;
+use crate::duration;
+use crate::paths;
+use crate::scale::ScaleMode;
+use crate::storage;
+use anyhow::{Context, Result, bail};
+use chrono::TimeDelta;
 use serde::{Deserialize, Serialize};
-use std::{env, fs, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(try_from = "ConfigFile")]
 pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal: Option<Goal>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub display: Display,
+    #[serde(skip_serializing_if = "is_default")]
+    pub behavior: Behavior,
+    #[serde(skip_serializing_if = "is_default")]
+    pub network: Network,
+    #[serde(skip_serializing_if = "is_default")]
+    pub users: Users,
+    #[serde(skip_serializing_if = "is_default")]
+    pub daemon: Daemon,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, ThemeConfig>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Defaults for how the graph is drawn. `--width` overrides `width`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Display {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u16>,
+    /// Rows printed in single mode, never fewer than the graph needs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u16>,
+    /// Columns per week, including the gap between weeks (default 3).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_width: Option<u16>,
+    /// A built-in theme or one from `[themes]`. `--theme` overrides it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// How days are drawn. `--cells` overrides it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cells: Option<CellMode>,
+    /// When to use colors. `--color` overrides it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorChoice>,
+    /// How counts map to colors. `--scale` overrides it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<ScaleMode>,
+    /// Lowest count of each of the four non-empty levels for the
+    /// `breakpoints` scale, e.g. `[1, 5, 10, 20]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breakpoints: Option<Vec<u32>>,
+    /// Five colors from no contributions to the most, e.g. `"#373737"`,
+    /// replacing the theme's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<[HexColor; 5]>,
+}
+
+/// Defaults for the matching command-line flags, which take precedence.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Behavior {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch: Option<u64>,
+    /// With `cached`, don't start a background update if the cache is younger
+    /// than this (default `1m`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_update_age: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Network {
+    /// Request timeout, e.g. `30s`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Proxy for every request, e.g. `http://proxy:3128`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Users {
+    /// Users `query` reports on when no `--user` is given and `daemon`
+    /// keeps fresh.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tracked: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Daemon {
+    /// How often every tracked user is refreshed (default `15m`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    /// Most users refreshed at once, counting those clients asked for
+    /// (default 50).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_users: Option<usize>,
+}
+
+impl Behavior {
+    pub fn max_age(&self) -> Result<Option<TimeDelta>> {
+        self.max_age
+            .as_deref()
+            .map(duration::parse)
+            .transpose()
+            .context("Invalid behavior.max_age")
+    }
+
+    pub fn min_update_age(&self) -> Result<TimeDelta> {
+        self.min_update_age
+            .as_deref()
+            .map_or(Ok(TimeDelta::minutes(1)), duration::parse)
+            .context("Invalid behavior.min_update_age")
+    }
+}
+
+impl Network {
+    pub fn timeout(&self) -> Result<Option<TimeDelta>> {
+        self.timeout
+            .as_deref()
+            .map(duration::parse)
+            .transpose()
+            .context("Invalid network.timeout")
+    }
+}
+
+impl Daemon {
+    pub fn interval(&self) -> Result<TimeDelta> {
+        self.interval
+            .as_deref()
+            .map_or(Ok(TimeDelta::minutes(15)), duration::parse)
+            .context("Invalid daemon.interval")
+    }
+
+    pub fn max_users(&self) -> usize {
+        self.max_users.unwrap_or(50)
+    }
+}
+
+/// A custom palette. Colors that aren't set come from `base`, a built-in
+/// theme (default `default`).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ThemeConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub levels: Option<[HexColor; 5]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<HexColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<HexColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub muted: Option<HexColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<HexColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal: Option<HexColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<HexColor>,
+}
+
+/// A `#rrggbb` color.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct HexColor(pub u8, pub u8, pub u8);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid color {:?}, expected #rrggbb", value);
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Self(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl From<HexColor> for String {
+    fn from(color: HexColor) -> Self {
+        format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+    }
+}
+
+/// On-disk layout, which also accepts the single-account fields of earlier
+/// versions and turns them into the `default` profile.
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+    #[serde(default)]
+    goal: Option<Goal>,
+    #[serde(default)]
+    display: Display,
+    #[serde(default)]
+    behavior: Behavior,
+    #[serde(default)]
+    network: Network,
+    #[serde(default)]
+    users: Users,
+    #[serde(default)]
+    daemon: Daemon,
+    #[serde(default)]
+    themes: BTreeMap<String, ThemeConfig>,
+
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    token: String,
+    #[serde(default)]
+    token_command: Option<String>,
+    #[serde(default)]
+    token_file: Option<PathBuf>,
+}
+
+/// Range of `display.week_width`: a cell and the gap, up to what fits in the
+/// narrowest terminal next to the day labels.
+const WEEK_WIDTHS: std::ops::RangeInclusive<u16> = 2..=10;
+
+impl TryFrom<ConfigFile> for Config {
+    type Error = String;
+
+    fn try_from(file: ConfigFile) -> Result<Self, Self::Error> {
+        if let Some(week_width) = file.display.week_width
+            && !WEEK_WIDTHS.contains(&week_width)
+        {
+            return Err(format!(
+                "display.week_width must be between {} and {}, got {}",
+                WEEK_WIDTHS.start(),
+                WEEK_WIDTHS.end(),
+                week_width
+            ));
+        }
+
+        let mut config = Self {
+            default_profile: file.default_profile,
+            profiles: file.profiles,
+            goal: file.goal,
+            display: file.display,
+            behavior: file.behavior,
+            network: file.network,
+            users: file.users,
+            daemon: file.daemon,
+            themes: file.themes,
+        };
+
+        if let Some(username) = file.username {
+            config
+                .profiles
+                .entry(DEFAULT_PROFILE.to_string())
+                .or_insert(Profile {
+                    username,
+                    token: file.token,
+                    token_command: file.token_command,
+                    token_file: file.token_file,
+                    ..Profile::default()
+                });
+        }
+
+        Ok(config)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    GitHub,
+    GitLab,
+}
+
+impl Provider {
+    pub fn default_host(self) -> &'static str {
+        match self {
+            Provider::GitHub => api::GITHUB_HOST,
+            Provider::GitLab => api::GITLAB_HOST,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Profile {
+    #[serde(default)]
+    pub provider: Provider,
+    /// Defaults to the provider's public host, set it for GitHub Enterprise
+    /// or self-hosted GitLab.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default)]
     pub username: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub token: String,
+    /// Shell command printing the token, e.g. `pass show github/trexanh`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+    /// File containing the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<PathBuf>,
 }
 
-impl Config {
-    fn path() -> Result<PathBuf> {
-        let home = env::var("HOME").context("HOME environment variable not set")?;
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Goal {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly: Option<u32>,
+    /// Local hour after which watch mode warns about a streak at risk.
+    #[serde(default = "Goal::default_warn_hour")]
+    pub warn_hour: u32,
+}
+
+impl Goal {
+    fn default_warn_hour() -> u32 {
+        20
+    }
+
+    pub fn is_met(&self, count: u32, week_total: u32) -> bool {
+        match (self.daily, self.weekly) {
+            (Some(daily), _) => count >= daily,
+            (None, Some(weekly)) => count > 0 && week_total >= weekly,
+            (None, None) => false,
+        }
+    }
+}
+
+impl Profile {
+    pub fn host(&self) -> &str {
+        self.host.as_deref().unwrap_or(self.provider.default_host())
+    }
 
-        Ok(PathBuf::from(home).join(".trexanh").join("config.json"))
+    pub fn has_token(&self) -> bool {
+        !self.token.is_empty() || self.token_command.is_some() || self.token_file.is_some()
+    }
+
+    /// Returns the token from `token_command`, `token_file` or the inline
+    /// `token`, in that order.
+    pub fn resolve_token(&self) -> Result<String> {
+        let token = if let Some(command) = &self.token_command {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .output()
+                .with_context(|| format!("Failed to run token_command `{}`", command))?;
+
+            if !output.status.success() {
+                bail!(
+                    "token_command `{}` failed: {}",
+                    command,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+
+            String::from_utf8(output.stdout).context("token_command printed invalid UTF-8")?
+        } else if let Some(file) = &self.token_file {
+            let file = expand_home(file);
+            fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read token_file {}", file.display()))?
+        } else {
+            self.token.clone()
+        };
+
+        let token = token.trim().to_string();
+        if token.is_empty() {
+            bail!("No token configured, run with --reset or set token_command / token_file");
+        }
+
+        Ok(token)
+    }
+}
+
+impl Config {
+    pub fn path() -> Result<PathBuf> {
+        paths::config_file()
     }
 
     pub fn exists() -> bool {
@@ -23,23 +416,299 @@ impl Config {
         let path = Self::path()?;
         let content = fs::read_to_string(&path)
             .with_context(|| format!("Failed to read config at {}", path.display()))?;
-        let config = serde_json::from_str(&content)
-            .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+        let config: Self = if is_json(&path) {
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid JSON in {}", path.display()))?
+        } else {
+            toml::from_str(&content)
+                .with_context(|| format!("Invalid TOML in {}", path.display()))?
+        };
+
+        if config
+            .profiles
+            .values()
+            .any(|profile| !profile.token.is_empty())
+            && let Ok(metadata) = fs::metadata(&path)
+        {
+            let mode = metadata.permissions().mode() & 0o777;
+            if mode & 0o077 != 0 {
+                eprintln!(
+                    "Warning: {} contains a token but has mode {:o}, run `chmod 600 {}`",
+                    path.display(),
+                    mode,
+                    path.display()
+                );
+            }
+        }
 
         Ok(config)
     }
 
+    /// The profile selected with `--profile`, else `default_profile`, else `default`.
+    pub fn profile_name(&self, requested: Option<&str>) -> String {
+        requested
+            .or(self.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+            .to_string()
+    }
+
     pub fn save(&self) -> Result<()> {
         let path = Self::path()?;
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        let content = if is_json(&path) {
+            serde_json::to_string_pretty(&self)?
+        } else {
+            toml::to_string_pretty(&self)?
+        };
+        storage::write_private(&path, &content)
+    }
+}
+
+/// Configs are TOML unless the file is named `*.json`, which older versions wrote.
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+const MASK: &str = "********";
+
+fn load_or_default() -> Result<Config> {
+    if Config::exists() {
+        Config::load()
+    } else {
+        Ok(Config::default())
+    }
+}
+
+fn to_table(config: &Config) -> Result<toml::Table> {
+    toml::Table::try_from(config).context("Failed to serialize config")
+}
+
+fn lookup<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut parts = key.split('.');
+    let mut value = table.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_table()?.get(part)?;
+    }
+    Some(value)
+}
+
+fn lookup_mut<'a>(table: &'a mut toml::Table, key: &str) -> Option<&'a mut toml::Table> {
+    key.split('.')
+        .try_fold(table, |table, part| table.get_mut(part)?.as_table_mut())
+}
+
+/// Prints the config as TOML with inline tokens masked.
+pub fn print() -> Result<()> {
+    let mut table = to_table(&load_or_default()?)?;
+
+    if let Some(toml::Value::Table(profiles)) = table.get_mut("profiles") {
+        for (_, profile) in profiles.iter_mut() {
+            if let Some(token) = profile.get_mut("token") {
+                *token = toml::Value::String(MASK.to_string());
+            }
         }
+    }
+
+    print!("{}", toml::to_string_pretty(&table)?);
+    Ok(())
+}
+
+pub fn print_key(key: &str) -> Result<()> {
+    let table = to_table(&load_or_default()?)?;
+    let value = lookup(&table, key).with_context(|| format!("{} is not set", key))?;
+
+    match value {
+        toml::Value::String(value) => println!("{}", value),
+        toml::Value::Table(table) => print!("{}", toml::to_string_pretty(table)?),
+        value => println!("{}", value),
+    }
+
+    Ok(())
+}
+
+/// Sets a dotted key such as `display.width` or `profiles.work.host`.
+pub fn set(key: &str, value: &str) -> Result<()> {
+    with_text(&load_or_default()?, key, value)?.save()
+}
+
+/// `value` is read as a TOML value if possible (`80`, `true`, `["a", "b"]`),
+/// otherwise as a string. If neither fits, the error is the TOML value's.
+fn with_text(config: &Config, key: &str, value: &str) -> Result<Config> {
+    let typed = toml::from_str::<toml::Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"));
+    let string = toml::Value::String(value.to_string());
+
+    match typed {
+        Some(typed) => with_value(config, key, typed)
+            .or_else(|err| with_value(config, key, string).map_err(|_| err)),
+        None => with_value(config, key, string),
+    }
+}
+
+/// Returns a copy of `config` with `key` set, failing if the result is not a
+/// valid config or the key isn't one trexanh knows.
+fn with_value(config: &Config, key: &str, value: toml::Value) -> Result<Config> {
+    let mut table = to_table(config)?;
+
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().filter(|part| !part.is_empty());
+    let Some(last) = last else {
+        bail!("Invalid key {:?}", key);
+    };
+
+    let mut current = &mut table;
+    for part in parts {
+        current = current
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .with_context(|| format!("{} is not a table", part))?;
+    }
+    current.insert(last.to_string(), value.clone());
+
+    let updated: Config = toml::Value::Table(table)
+        .try_into()
+        .with_context(|| format!("Invalid value for {}", key))?;
+
+    if lookup(&to_table(&updated)?, key) != Some(&value) {
+        bail!("Unknown key {}", key);
+    }
+
+    Ok(updated)
+}
+
+pub fn unset(key: &str) -> Result<()> {
+    without_key(&load_or_default()?, key)?.save()
+}
+
+/// Returns a copy of `config` without `key`, failing if it wasn't set or
+/// the config would be invalid without it.
+fn without_key(config: &Config, key: &str) -> Result<Config> {
+    let mut table = to_table(config)?;
+
+    let (parent, last) = match key.rsplit_once('.') {
+        Some((parent, last)) => (lookup_mut(&mut table, parent), last),
+        None => (Some(&mut table), key),
+    };
+    if parent.and_then(|parent| parent.remove(last)).is_none() {
+        bail!("{} is not set", key);
+    }
+
+    toml::Value::Table(table)
+        .try_into()
+        .with_context(|| format!("Config would be invalid without {}", key))
+}
+
+/// Opens the config in `$VISUAL` / `$EDITOR` and checks that it still parses.
+pub fn edit() -> Result<()> {
+    let path = Config::path()?;
+    if !path.exists() {
+        storage::write_private(&path, "")?;
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // The editor is passed through the environment so it is split into
+    // words like `code --wait`, but never parsed as shell code.
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg("$TREXANH_EDITOR \"$1\"")
+        .arg("sh")
+        .env("TREXANH_EDITOR", &editor)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to run {}", editor))?;
+
+    if !status.success() {
+        bail!("{} exited with {}", editor, status);
+    }
+
+    Config::load().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    fn error(result: Result<Config>) -> String {
+        format!("{:#}", result.expect_err("should fail"))
+    }
+
+    #[test]
+    fn sets_typed_values() {
+        let config = with_text(&Config::default(), "display.width", "80").unwrap();
+        assert_eq!(config.display.width, Some(80));
+
+        let config = with_text(&config, "users.tracked", r#"["alice", "bob"]"#).unwrap();
+        assert_eq!(config.users.tracked, ["alice", "bob"]);
+    }
+
+    #[test]
+    fn falls_back_to_strings() {
+        let config = with_text(&Config::default(), "display.theme", "github-dark").unwrap();
+        assert_eq!(config.display.theme.as_deref(), Some("github-dark"));
+
+        let config = with_text(&config, "profiles.work.username", "1234").unwrap();
+        assert_eq!(config.profiles["work"].username, "1234");
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        let config = Config::default();
+
+        assert!(error(with_text(&config, "display.nope", "1")).starts_with("Unknown key"));
+        assert!(error(with_text(&config, "display.", "1")).starts_with("Invalid key"));
+        let config = with_text(&config, "display.width", "80").unwrap();
+        assert_eq!(
+            error(with_text(&config, "display.width.x", "1")),
+            "width is not a table"
+        );
+        assert!(
+            error(with_text(&config, "display.week_width", "40"))
+                .contains("must be between 2 and 10")
+        );
+    }
+
+    #[test]
+    fn unsets_keys_that_are_set() {
+        let config = with_text(&Config::default(), "display.width", "80").unwrap();
+        let config = with_text(&config, "profiles.work.host", "github.example.com").unwrap();
+
+        let without = without_key(&config, "display.width").unwrap();
+        assert_eq!(without.display.width, None);
+        let without = without_key(&without, "profiles.work").unwrap();
+        assert!(without.profiles.is_empty());
+
+        assert_eq!(
+            error(without_key(&without, "display.width")),
+            "display.width is not set"
+        );
+        assert!(error(without_key(&without, "nope.width")).ends_with("is not set"));
+    }
 
-        let json = serde_json::to_string_pretty(&self)?;
-        fs::write(&path, json)
-            .with_context(|| format!("Failed to write config to {}", path.display()))?;
+    #[test]
+    fn bounds_the_week_width() {
+        assert!(parse("[display]\nweek_width = 2").is_ok());
+        assert!(parse("[display]\nweek_width = 10").is_ok());
 
-        Ok(())
+        let err = parse("[display]\nweek_width = 40").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("display.week_width must be between 2 and 10")
+        );
+        assert!(parse("[display]\nweek_width = 1").is_err());
     }
 }
@@ -0,0 +1,246 @@
+use crate::api::RateLimited;
+use crate::app::App;
+use crate::background::{self, log};
+use crate::cache::{self, Cache, CacheKey, CachedEntry};
+use crate::config::{Config, Profile};
+use crate::diff;
+use crate::paths;
+use crate::storage::FileLock;
+use anyhow::{Context, Result, bail};
+use chrono::{TimeDelta, Utc};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{fs::PermissionsExt, net::UnixStream},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    net::UnixListener,
+    sync::Mutex,
+    time::sleep,
+};
+
+/// How long a client waits for the daemon before falling back to the cache file.
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(500);
+
+fn socket_path() -> Result<PathBuf> {
+    paths::cache_file("daemon.sock")
+}
+
+struct State {
+    /// Logins refreshed every round. Clients asking for an unknown login on
+    /// the daemon's host are added here, up to `max_users`.
+    users: Vec<String>,
+    max_users: usize,
+    entries: HashMap<CacheKey, CachedEntry>,
+}
+
+impl State {
+    /// Starts refreshing `key` if it is the rolling year of a plausible login
+    /// on the daemon's host and there's room for another user.
+    fn track(&mut self, key: &CacheKey, host: &str) {
+        let valid = key.host == host
+            && key.is_default_scope()
+            && !key.login.is_empty()
+            && key.login.len() <= 100
+            && key.login.chars().all(|c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.')
+            });
+        let known = self
+            .users
+            .iter()
+            .any(|user| user.eq_ignore_ascii_case(&key.login));
+
+        if !valid || known {
+            return;
+        }
+        if self.users.len() >= self.max_users {
+            log(&format!(
+                "Not tracking @{}, already at daemon.max_users ({})",
+                key.login, self.max_users
+            ));
+            return;
+        }
+
+        log(&format!("Now tracking @{}", key.login));
+        self.users.push(key.login.clone());
+    }
+}
+
+/// Refreshes the profile's user and `users.tracked` once per `interval`,
+/// spreading the requests over it, and serves the results on a Unix socket.
+pub async fn run(config: Config, profile: Profile, interval: TimeDelta) -> Result<()> {
+    if interval <= TimeDelta::zero() {
+        bail!("The daemon interval must be positive");
+    }
+
+    let path = socket_path()?;
+    let Some(_lock) = FileLock::try_exclusive(&path)? else {
+        bail!("trexanh daemon is already running on {}", path.display());
+    };
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    // Only the user running the daemon may ask it to track someone.
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to restrict {}", path.display()))?;
+
+    let mut users = vec![profile.username.clone()];
+    for user in &config.users.tracked {
+        if !users.iter().any(|known| known.eq_ignore_ascii_case(user)) {
+            users.push(user.clone());
+        }
+    }
+
+    let cache = Cache::load()?;
+    for warning in cache.warnings() {
+        log(warning);
+    }
+    let entries = users
+        .iter()
+        .filter_map(|user| {
+            let key = CacheKey::new(profile.host(), user);
+            let entry = cache
+                .get(&key)
+                .filter(|entry| entry.has_calendar())?
+                .clone();
+            Some((key, entry))
+        })
+        .collect();
+
+    log(&format!(
+        "Tracking {} users on {}, serving on {}",
+        users.len(),
+        profile.host(),
+        path.display()
+    ));
+    println!(
+        "Serving on {}, logging to {}",
+        path.display(),
+        background::log_path()?.display()
+    );
+
+    let state = Arc::new(Mutex::new(State {
+        users,
+        max_users: config.daemon.max_users(),
+        entries,
+    }));
+
+    tokio::spawn(serve(
+        listener,
+        Arc::clone(&state),
+        profile.host().to_string(),
+    ));
+
+    refresh_loop(config, profile, interval, state).await
+}
+
+async fn refresh_loop(
+    config: Config,
+    profile: Profile,
+    interval: TimeDelta,
+    state: Arc<Mutex<State>>,
+) -> Result<()> {
+    loop {
+        let users = state.lock().await.users.clone();
+        let spacing = (interval / users.len().max(1) as i32)
+            .to_std()
+            .unwrap_or(Duration::from_secs(1));
+
+        for username in users {
+            let mut app = App::new(
+                config.clone(),
+                Profile {
+                    username: username.clone(),
+                    ..profile.clone()
+                },
+            );
+            let key = app.cache_key();
+
+            loop {
+                match app.refresh_cache().await {
+                    Ok(entry) => {
+                        let summary = diff::summary(&entry.changes)
+                            .map(|summary| format!(" ({})", summary))
+                            .unwrap_or_default();
+                        log(&format!("Refreshed @{}{}", username, summary));
+                        state.lock().await.entries.insert(key, entry);
+                    }
+                    Err(err) => {
+                        if let Some(limit) = err.downcast_ref::<RateLimited>() {
+                            log(&format!("{}, pausing", limit));
+                            let wait = (limit.until - Utc::now()).to_std().unwrap_or_default();
+                            sleep(wait + Duration::from_secs(1)).await;
+                            continue;
+                        }
+                        log(&format!("Failed to refresh @{}: {:#}", username, err));
+                        if let Err(err) = cache::record_error(&key, &err) {
+                            log(&format!("Failed to record the error: {:#}", err));
+                        }
+                    }
+                }
+                break;
+            }
+
+            sleep(spacing).await;
+        }
+    }
+}
+
+/// Answers one cache key per line with the entry as JSON, or `null`.
+async fn serve(listener: UnixListener, state: Arc<Mutex<State>>, host: String) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let state = Arc::clone(&state);
+        let host = host.clone();
+
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = tokio::io::BufReader::new(reader).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let Some(key) = CacheKey::parse(line.trim()) else {
+                    break;
+                };
+
+                let response = {
+                    let mut state = state.lock().await;
+                    let entry = state.entries.get(&key).cloned();
+                    if entry.is_none() {
+                        state.track(&key, &host);
+                    }
+
+                    serde_json::to_string(&entry).unwrap_or_else(|_| "null".to_string())
+                };
+
+                if writer
+                    .write_all(format!("{}\n", response).as_bytes())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Asks a running daemon for `key`. `None` if no daemon answers or it
+/// doesn't have the user yet.
+pub fn request(key: &CacheKey) -> Option<CachedEntry> {
+    let mut stream = UnixStream::connect(socket_path().ok()?).ok()?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+
+    writeln!(stream, "{}", key).ok()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+    serde_json::from_str(&line).ok()?
+}
@@ -0,0 +1,406 @@
+use crate::cache::CacheKey;
+use crate::history::Record;
+use crate::paths;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use std::{fs, io, path::PathBuf, time::Duration};
+
+/// Applied in order on open; `PRAGMA user_version` counts those already run.
+const MIGRATIONS: [&str; 3] = [
+    "CREATE TABLE IF NOT EXISTS days (
+        user TEXT NOT NULL,
+        host TEXT NOT NULL,
+        date TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        fetched_at TEXT NOT NULL,
+        PRIMARY KEY (user, host, date, fetched_at)
+    );
+    CREATE INDEX IF NOT EXISTS days_by_fetch ON days (user, host, fetched_at);",
+    // Records imported from the JSON lines log kept the login as typed.
+    "UPDATE OR REPLACE days SET user = lower(trim(user)), host = lower(host);",
+    "CREATE TABLE entries (
+        key TEXT PRIMARY KEY,
+        version INTEGER NOT NULL,
+        entry TEXT NOT NULL
+    );",
+];
+
+/// Rows holding the most recently fetched count of every day.
+const LATEST: &str = "
+    SELECT user, host, date, count FROM days AS d
+    WHERE fetched_at = (
+        SELECT MAX(fetched_at) FROM days
+        WHERE user = d.user AND host = d.host AND date = d.date
+    )
+";
+
+pub struct Observation {
+    pub fetched_at: DateTime<Utc>,
+    pub date: String,
+    pub count: u32,
+}
+
+pub struct Aggregate {
+    pub period: String,
+    pub user: String,
+    pub host: String,
+    pub total: u64,
+    pub active_days: u64,
+    pub best_day: u32,
+}
+
+/// A cached graph as stored, JSON in the schema version it was written with.
+pub struct StoredEntry {
+    pub key: String,
+    pub version: u32,
+    pub entry: String,
+}
+
+/// Local SQLite database holding the cached graphs and every day's count as
+/// seen at each fetch.
+///
+/// Days are stored as deltas: a fetch only writes rows for the days whose
+/// count differs from the last one stored, so a day's count at any fetch is
+/// that of its latest row up to then, and 0 before its first row.
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    fn path() -> Result<PathBuf> {
+        paths::cache_file("history.db")
+    }
+
+    pub fn open() -> Result<Self> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+
+        let conn = Connection::open(&path)
+            .with_context(|| format!("Failed to open database at {}", path.display()))?;
+        let mut db = Self::init(conn)
+            .with_context(|| format!("Failed to initialize database at {}", path.display()))?;
+        db.import_legacy_log()?;
+
+        Ok(db)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.busy_timeout(Duration::from_secs(5))?;
+        let mut db = Self { conn };
+        db.immediate(|db| db.migrate())?;
+        Ok(db)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let version: usize = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            self.conn.execute_batch(migration)?;
+            self.conn.pragma_update(None, "user_version", index + 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs `f` in a transaction that takes the write lock up front, so
+    /// concurrent read-modify-write cycles don't lose each other's changes.
+    pub fn immediate<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+
+        match f(self) {
+            Ok(result) => {
+                self.conn.execute_batch("COMMIT")?;
+                Ok(result)
+            }
+            Err(err) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(err)
+            }
+        }
+    }
+
+    pub fn insert(&mut self, record: &Record) -> Result<()> {
+        let tx = self.conn.savepoint()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "INSERT OR REPLACE INTO days (user, host, date, count, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for change in &record.changes {
+                statement.execute(params![
+                    record.username,
+                    record.host,
+                    change.date,
+                    change.after,
+                    record.fetched_at
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Every stored row of one user, oldest fetch first.
+    pub fn observations(&self, user: &str, host: &str) -> Result<Vec<Observation>> {
+        let mut statement = self.conn.prepare(
+            "SELECT fetched_at, date, count FROM days
+             WHERE user = ?1 AND host = ?2
+             ORDER BY fetched_at, date",
+        )?;
+
+        let rows = statement.query_map(params![user, host], |row| {
+            Ok(Observation {
+                fetched_at: row.get(0)?,
+                date: row.get(1)?,
+                count: row.get(2)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Sums the latest count of each day, grouped by `period_format`
+    /// (a SQLite `strftime` format, or `None` for one row per user) and by
+    /// user and host.
+    pub fn aggregate(
+        &self,
+        period_format: Option<&str>,
+        users: &[String],
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<Aggregate>> {
+        let period = match period_format {
+            Some(_) => "strftime(?1, date)",
+            None => "'all'",
+        };
+
+        let sql = format!(
+            "SELECT {period} AS period, user, host, SUM(count), SUM(count > 0), MAX(count)
+             FROM ({LATEST})
+             WHERE (?2 IS NULL OR date >= ?2) AND (?3 IS NULL OR date <= ?3)
+             GROUP BY period, user, host
+             ORDER BY period, user, host"
+        );
+
+        let mut statement = self.conn.prepare(&sql)?;
+        let rows = statement.query_map(params![period_format, since, until], |row| {
+            Ok(Aggregate {
+                period: row.get(0)?,
+                user: row.get(1)?,
+                host: row.get(2)?,
+                total: row.get(3)?,
+                active_days: row.get(4)?,
+                best_day: row.get(5)?,
+            })
+        })?;
+
+        let mut aggregates = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+        if !users.is_empty() {
+            aggregates.retain(|row| {
+                users
+                    .iter()
+                    .any(|user| user.eq_ignore_ascii_case(&row.user))
+            });
+        }
+
+        Ok(aggregates)
+    }
+
+    pub fn entries(&self) -> Result<Vec<StoredEntry>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT key, version, entry FROM entries ORDER BY key")?;
+
+        let rows = statement.query_map([], |row| {
+            Ok(StoredEntry {
+                key: row.get(0)?,
+                version: row.get(1)?,
+                entry: row.get(2)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn put_entry(&self, entry: &StoredEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO entries (key, version, entry) VALUES (?1, ?2, ?3)",
+            params![entry.key, entry.version, entry.entry],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_entry(&self, key: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM entries WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    /// Moves records from the JSON lines log used by earlier versions into
+    /// the database, then renames the log so it is only imported once.
+    fn import_legacy_log(&mut self) -> Result<()> {
+        let path = paths::cache_file("history.jsonl")?;
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read history at {}", path.display()))?;
+
+        for (line_idx, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut record: Record = serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Invalid JSON on line {} of {}",
+                    line_idx + 1,
+                    path.display()
+                )
+            })?;
+
+            let key = CacheKey::new(&record.host, &record.username);
+            record.username = key.login;
+            record.host = key.host;
+            self.insert(&record)?;
+        }
+
+        // Another process may have imported and renamed it in the meantime.
+        let imported = path.with_extension("jsonl.imported");
+        match fs::rename(&path, &imported) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Failed to rename {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DayChange;
+
+    fn record(username: &str, fetched_at: &str, changes: &[(&str, u32)]) -> Record {
+        Record {
+            username: username.to_string(),
+            host: "github.com".to_string(),
+            fetched_at: fetched_at.parse().unwrap(),
+            baseline: false,
+            changes: changes
+                .iter()
+                .map(|&(date, after)| DayChange {
+                    date: date.to_string(),
+                    before: 0,
+                    after,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn stores_only_changed_days_and_aggregates_the_latest() {
+        let mut db = Db::open_in_memory().unwrap();
+        db.insert(&record(
+            "alice",
+            "2025-03-01T00:00:00Z",
+            &[("2025-02-27", 2), ("2025-02-28", 5)],
+        ))
+        .unwrap();
+        db.insert(&record(
+            "alice",
+            "2025-03-02T00:00:00Z",
+            &[("2025-02-28", 7)],
+        ))
+        .unwrap();
+
+        let observations = db.observations("alice", "github.com").unwrap();
+        let rows: Vec<_> = observations
+            .iter()
+            .map(|o| (o.fetched_at.to_rfc3339(), o.date.as_str(), o.count))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("2025-03-01T00:00:00+00:00".to_string(), "2025-02-27", 2),
+                ("2025-03-01T00:00:00+00:00".to_string(), "2025-02-28", 5),
+                ("2025-03-02T00:00:00+00:00".to_string(), "2025-02-28", 7),
+            ]
+        );
+
+        let aggregates = db.aggregate(None, &[], None, None).unwrap();
+        assert_eq!(aggregates.len(), 1);
+        assert_eq!(aggregates[0].total, 9);
+        assert_eq!(aggregates[0].active_days, 2);
+        assert_eq!(aggregates[0].best_day, 7);
+    }
+
+    #[test]
+    fn aggregates_each_host_separately() {
+        let mut db = Db::open_in_memory().unwrap();
+        db.insert(&record(
+            "alice",
+            "2025-03-01T00:00:00Z",
+            &[("2025-02-28", 5)],
+        ))
+        .unwrap();
+        db.insert(&Record {
+            host: "gitlab.com".to_string(),
+            ..record("alice", "2025-03-01T00:00:00Z", &[("2025-02-28", 2)])
+        })
+        .unwrap();
+
+        let aggregates = db.aggregate(None, &[], None, None).unwrap();
+        let rows: Vec<_> = aggregates
+            .iter()
+            .map(|row| (row.host.as_str(), row.total))
+            .collect();
+        assert_eq!(rows, [("github.com", 5), ("gitlab.com", 2)]);
+    }
+
+    #[test]
+    fn migration_lowercases_imported_logins() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute_batch(
+            "INSERT INTO days VALUES ('Alice', 'GitHub.com', '2025-02-28', 5, '2025-03-01T00:00:00Z');
+             INSERT INTO days VALUES ('alice', 'github.com', '2025-02-28', 5, '2025-03-01T00:00:00Z');
+             INSERT INTO days VALUES ('Bob', 'github.com', '2025-02-28', 1, '2025-03-01T00:00:00Z');",
+        )
+        .unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+
+        let db = Db::init(conn).unwrap();
+
+        assert_eq!(db.observations("alice", "github.com").unwrap().len(), 1);
+        assert_eq!(db.observations("bob", "github.com").unwrap().len(), 1);
+        let version: usize = db
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn migrations_run_on_databases_without_a_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+
+        let db = Db::init(conn).unwrap();
+
+        assert!(db.entries().unwrap().is_empty());
+    }
+}
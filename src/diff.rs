@@ -0,0 +1,191 @@
+use crate::models::ContributionCalendar;
+use crate::stats;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DayChange {
+    pub date: String,
+    pub before: u32,
+    pub after: u32,
+}
+
+impl DayChange {
+    pub fn delta(&self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+}
+
+/// Compares two calendars day by day. Days that only rolled out of the
+/// window of `new` are not reported.
+pub fn diff(old: &ContributionCalendar, new: &ContributionCalendar) -> Vec<DayChange> {
+    let counts = |calendar: &ContributionCalendar| -> HashMap<String, u32> {
+        calendar
+            .weeks
+            .iter()
+            .flat_map(|week| week.contribution_days.iter())
+            .map(|day| (day.date.clone(), day.contribution_count))
+            .collect()
+    };
+
+    let old_counts = counts(old);
+    let new_counts = counts(new);
+
+    let Some(first_date) = new_counts.keys().min().cloned() else {
+        return vec![];
+    };
+
+    let mut dates: Vec<&String> = old_counts
+        .keys()
+        .filter(|date| **date >= first_date)
+        .chain(new_counts.keys())
+        .collect();
+    dates.sort();
+    dates.dedup();
+
+    dates
+        .into_iter()
+        .filter_map(|date| {
+            let before = old_counts.get(date).copied().unwrap_or(0);
+            let after = new_counts.get(date).copied().unwrap_or(0);
+
+            (before != after).then(|| DayChange {
+                date: date.clone(),
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+pub fn summary(changes: &[DayChange]) -> Option<String> {
+    const MAX_LISTED: usize = 3;
+
+    if changes.is_empty() {
+        return None;
+    }
+
+    let today = stats::today_date().format("%Y-%m-%d").to_string();
+    let mut parts = vec![];
+
+    if let Some(change) = changes.iter().find(|change| change.date == today) {
+        parts.push(format!("{:+} today", change.delta()));
+    }
+
+    let past: Vec<&DayChange> = changes
+        .iter()
+        .filter(|change| change.date != today)
+        .collect();
+
+    for change in past.iter().take(MAX_LISTED) {
+        let date = change
+            .date
+            .parse::<NaiveDate>()
+            .map(|date| date.format("%b %-d").to_string())
+            .unwrap_or_else(|_| change.date.clone());
+
+        let part = if change.after == 0 {
+            format!("{} removed on {}", -change.delta(), date)
+        } else if change.delta() > 0 {
+            format!("{:+} backfilled on {}", change.delta(), date)
+        } else {
+            format!("{:+} rewritten on {}", change.delta(), date)
+        };
+        parts.push(part);
+    }
+
+    if past.len() > MAX_LISTED {
+        parts.push(format!("{} more days changed", past.len() - MAX_LISTED));
+    }
+
+    Some(parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContributionDay, Week};
+
+    fn calendar(days: &[(&str, u32)]) -> ContributionCalendar {
+        ContributionCalendar {
+            weeks: vec![Week {
+                contribution_days: days
+                    .iter()
+                    .map(|&(date, count)| ContributionDay {
+                        date: date.to_string(),
+                        contribution_count: count,
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    fn change(date: &str, before: u32, after: u32) -> DayChange {
+        DayChange {
+            date: date.to_string(),
+            before,
+            after,
+        }
+    }
+
+    fn changed(changes: &[DayChange]) -> Vec<(&str, u32, u32)> {
+        changes
+            .iter()
+            .map(|change| (change.date.as_str(), change.before, change.after))
+            .collect()
+    }
+
+    #[test]
+    fn reports_changed_added_and_removed_days() {
+        let old = calendar(&[("2025-01-01", 1), ("2025-01-02", 2), ("2025-01-03", 3)]);
+        let new = calendar(&[("2025-01-01", 1), ("2025-01-02", 5), ("2025-01-04", 1)]);
+
+        assert_eq!(
+            changed(&diff(&old, &new)),
+            [
+                ("2025-01-02", 2, 5),
+                ("2025-01-03", 3, 0),
+                ("2025-01-04", 0, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_days_that_rolled_out_of_the_window() {
+        let old = calendar(&[("2025-01-01", 4), ("2025-01-02", 2)]);
+        let new = calendar(&[("2025-01-02", 2), ("2025-01-03", 0)]);
+
+        assert!(diff(&old, &new).is_empty());
+        assert!(diff(&old, &calendar(&[])).is_empty());
+    }
+
+    #[test]
+    fn summarizes_today_and_past_days() {
+        let today = stats::today_date().format("%Y-%m-%d").to_string();
+        let changes = [
+            change("2025-03-01", 0, 2),
+            change(&today, 1, 5),
+            change("2025-03-02", 4, 0),
+            change("2025-03-03", 5, 3),
+        ];
+
+        assert_eq!(
+            summary(&changes).unwrap(),
+            "+4 today, +2 backfilled on Mar 1, 4 removed on Mar 2, -2 rewritten on Mar 3"
+        );
+        assert_eq!(summary(&[]), None);
+    }
+
+    #[test]
+    fn summary_lists_at_most_three_past_days() {
+        let changes: Vec<_> = (1..=5)
+            .map(|day| change(&format!("2025-03-0{}", day), 0, 1))
+            .collect();
+
+        assert_eq!(
+            summary(&changes).unwrap(),
+            "+1 backfilled on Mar 1, +1 backfilled on Mar 2, +1 backfilled on Mar 3, 2 more days changed"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Parses durations such as `90` (seconds), `90s`, `30m`, `3h`, `2d` or `1w`.
+pub fn parse(input: &str) -> Result<TimeDelta> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration '{}', expected e.g. 30m, 3h or 2d", input))?;
+
+    let delta = match unit {
+        "s" | "" => TimeDelta::try_seconds(amount),
+        "m" => TimeDelta::try_minutes(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => None,
+    };
+
+    delta.ok_or_else(|| anyhow!("Invalid duration '{}', expected e.g. 30m, 3h or 2d", input))
+}
+
+pub fn format_age(time: DateTime<Utc>) -> String {
+    let age = Utc::now() - time;
+
+    if age.num_minutes() < 1 {
+        "just now".to_string()
+    } else if age.num_hours() < 1 {
+        format!("{}m ago", age.num_minutes())
+    } else if age.num_days() < 1 {
+        format!("{}h ago", age.num_hours())
+    } else if age.num_weeks() < 2 {
+        format!("{}d ago", age.num_days())
+    } else {
+        format!("{}w ago", age.num_weeks())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_unit() {
+        assert_eq!(parse("90").unwrap(), TimeDelta::seconds(90));
+        assert_eq!(parse("90s").unwrap(), TimeDelta::seconds(90));
+        assert_eq!(parse("30m").unwrap(), TimeDelta::minutes(30));
+        assert_eq!(parse(" 3h ").unwrap(), TimeDelta::hours(3));
+        assert_eq!(parse("2d").unwrap(), TimeDelta::days(2));
+        assert_eq!(parse("1w").unwrap(), TimeDelta::weeks(1));
+    }
+
+    #[test]
+    fn rejects_bad_suffixes() {
+        for input in ["3x", "3hours", "3 h", "3H", "3h30m", "2d!"] {
+            assert!(parse(input).is_err(), "{input} should be rejected");
+        }
+    }
+
+    #[test]
+    fn rejects_missing_or_signed_amounts() {
+        for input in ["", "h", "-3h", "+3h", "1.5h"] {
+            assert!(parse(input).is_err(), "{input} should be rejected");
+        }
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert!(parse("99999999999999999999s").is_err());
+        assert!(parse("9223372036854775807w").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::api;
+use crate::db::Db;
+use crate::diff::DayChange;
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The days whose counts differed from the previous fetch of the same user.
+/// Deserializable for importing the JSON lines log of earlier versions.
+#[derive(Deserialize)]
+pub struct Record {
+    pub username: String,
+    #[serde(default = "default_host")]
+    pub host: String,
+    pub fetched_at: DateTime<Utc>,
+    /// Set on the first fetch of a user, when `changes` holds every non-zero day.
+    #[serde(default)]
+    pub baseline: bool,
+    pub changes: Vec<DayChange>,
+}
+
+fn default_host() -> String {
+    api::GITHUB_HOST.to_string()
+}
+
+impl Record {
+    fn kind(&self, change: &DayChange) -> &'static str {
+        let fetched_on = self
+            .fetched_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d")
+            .to_string();
+
+        if self.baseline {
+            "first seen"
+        } else if change.after == 0 {
+            "disappeared"
+        } else if change.delta() < 0 {
+            "rewritten"
+        } else if change.date >= fetched_on {
+            "new"
+        } else {
+            "backdated"
+        }
+    }
+}
+
+pub fn append(record: &Record) -> Result<()> {
+    Db::open()?.insert(record)
+}
+
+/// Rebuilds the per-fetch records of one user from the stored rows.
+pub fn load(username: &str, host: &str) -> Result<Vec<Record>> {
+    let username = username.trim().to_lowercase();
+    let observations = Db::open()?.observations(&username, host)?;
+
+    let mut records: Vec<Record> = vec![];
+    let mut counts: HashMap<String, u32> = HashMap::new();
+
+    for observation in observations {
+        if records
+            .last()
+            .is_none_or(|record| record.fetched_at != observation.fetched_at)
+        {
+            records.push(Record {
+                username: username.clone(),
+                host: host.to_string(),
+                fetched_at: observation.fetched_at,
+                baseline: records.is_empty(),
+                changes: vec![],
+            });
+        }
+
+        let before = counts
+            .insert(observation.date.clone(), observation.count)
+            .unwrap_or(0);
+
+        if let Some(record) = records.last_mut()
+            && (record.baseline || before != observation.count)
+        {
+            record.changes.push(DayChange {
+                date: observation.date,
+                before,
+                after: observation.count,
+            });
+        }
+    }
+
+    Ok(records)
+}
+
+pub fn print(username: &str, host: &str, date: Option<NaiveDate>, all: bool) -> Result<()> {
+    let date = date.map(|date| date.format("%Y-%m-%d").to_string());
+    let records = load(username, host)?;
+
+    let mut printed = 0;
+
+    for record in &records {
+        if record.baseline && !all && date.is_none() {
+            continue;
+        }
+
+        let fetched_at = record.fetched_at.with_timezone(&Local);
+
+        for change in &record.changes {
+            if date.as_ref().is_some_and(|date| *date != change.date) {
+                continue;
+            }
+
+            println!(
+                "{}  {}  {:>4} -> {:<4} {}",
+                fetched_at.format("%Y-%m-%d %H:%M"),
+                change.date,
+                change.before,
+                change.after,
+                record.kind(change)
+            );
+            printed += 1;
+        }
+    }
+
+    if printed == 0 {
+        println!("No recorded changes for @{}", username);
+    }
+
+    Ok(())
+}
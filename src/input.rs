@@ -0,0 +1,287 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_width::UnicodeWidthChar;
+
+/// Single-line text field for the setup screen. `cursor` is a byte offset
+/// that always sits on a char boundary.
+#[derive(Default)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            cursor: text.len(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Applies an editing key and returns whether it was used.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('u') if ctrl => self.delete_to_start(),
+            KeyCode::Char('k') if ctrl => self.text.truncate(self.cursor),
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.len(),
+            KeyCode::Char(_) if ctrl => return false,
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Backspace => {
+                if let Some(prev) = self.prev_boundary() {
+                    self.text.drain(prev..self.cursor);
+                    self.cursor = prev;
+                }
+            }
+            KeyCode::Delete => {
+                if let Some(next) = self.next_boundary() {
+                    self.text.drain(self.cursor..next);
+                }
+            }
+            KeyCode::Left => self.cursor = self.prev_boundary().unwrap_or(0),
+            KeyCode::Right => self.cursor = self.next_boundary().unwrap_or(self.text.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Inserts pasted text, dropping line breaks and other control characters.
+    pub fn paste(&mut self, text: &str) {
+        for c in text.chars().filter(|c| !c.is_control()) {
+            self.insert(c);
+        }
+    }
+
+    fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    fn prev_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+
+    /// Ctrl-W: removes the word before the cursor and the spaces after it.
+    fn delete_word(&mut self) {
+        let before = &self.text[..self.cursor];
+        let trimmed = before.trim_end();
+        let start = trimmed
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    fn delete_to_start(&mut self) {
+        self.text.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    /// The part of the text that fits in `width` columns, scrolled so the
+    /// cursor stays visible, and the cursor's column within it. Masked
+    /// fields show one `•` per character.
+    pub fn view(&self, width: usize, masked: bool) -> (String, usize) {
+        let chars: Vec<char> = if masked {
+            self.text.chars().map(|_| '•').collect()
+        } else {
+            self.text.chars().collect()
+        };
+        let cursor = self.text[..self.cursor].chars().count();
+        let char_width = |c: &char| c.width().unwrap_or(0);
+
+        // Leave a column for the cursor when it sits at the end.
+        let mut start = cursor;
+        let mut used = 1;
+        while start > 0 && used + char_width(&chars[start - 1]) <= width {
+            start -= 1;
+            used += char_width(&chars[start]);
+        }
+
+        let mut visible = String::new();
+        let mut visible_width = 0;
+        let mut cursor_col = 0;
+        for (i, c) in chars.iter().enumerate().skip(start) {
+            if visible_width + char_width(c) > width {
+                break;
+            }
+            if i == cursor {
+                cursor_col = visible_width;
+            }
+            visible.push(*c);
+            visible_width += char_width(c);
+        }
+        if cursor == chars.len() {
+            cursor_col = visible_width;
+        }
+
+        (visible, cursor_col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut LineEditor, code: KeyCode) {
+        editor.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn ctrl(editor: &mut LineEditor, c: char) {
+        editor.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+    }
+
+    fn typed(text: &str) -> LineEditor {
+        let mut editor = LineEditor::default();
+        for c in text.chars() {
+            press(&mut editor, KeyCode::Char(c));
+        }
+        editor
+    }
+
+    #[test]
+    fn edits_around_multibyte_and_wide_chars() {
+        let mut editor = typed("hél");
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.text(), "hl");
+
+        press(&mut editor, KeyCode::Char('日'));
+        assert_eq!(editor.text(), "h日l");
+
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "h日");
+
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "h");
+    }
+
+    #[test]
+    fn moves_past_the_ends_without_panicking() {
+        let mut editor = typed("ü");
+        press(&mut editor, KeyCode::Right);
+        press(&mut editor, KeyCode::Delete);
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.text(), "ü");
+    }
+
+    #[test]
+    fn jumps_to_start_and_end() {
+        let mut editor = LineEditor::new("abc");
+        press(&mut editor, KeyCode::Home);
+        press(&mut editor, KeyCode::Char('x'));
+        press(&mut editor, KeyCode::End);
+        press(&mut editor, KeyCode::Char('y'));
+        assert_eq!(editor.text(), "xabcy");
+
+        ctrl(&mut editor, 'a');
+        press(&mut editor, KeyCode::Char('<'));
+        ctrl(&mut editor, 'e');
+        press(&mut editor, KeyCode::Char('>'));
+        assert_eq!(editor.text(), "<xabcy>");
+    }
+
+    #[test]
+    fn ctrl_w_deletes_the_word_before_the_cursor() {
+        let mut editor = LineEditor::new("foo bar  ");
+        ctrl(&mut editor, 'w');
+        assert_eq!(editor.text(), "foo ");
+        ctrl(&mut editor, 'w');
+        assert_eq!(editor.text(), "");
+
+        let mut editor = LineEditor::new("foo bär baz");
+        for _ in 0..4 {
+            press(&mut editor, KeyCode::Left);
+        }
+        ctrl(&mut editor, 'w');
+        assert_eq!(editor.text(), "foo  baz");
+        press(&mut editor, KeyCode::Char('x'));
+        assert_eq!(editor.text(), "foo x baz");
+    }
+
+    #[test]
+    fn ctrl_u_and_ctrl_k_delete_to_either_end() {
+        let mut editor = LineEditor::new("foo bar");
+        for _ in 0..3 {
+            press(&mut editor, KeyCode::Left);
+        }
+        ctrl(&mut editor, 'u');
+        assert_eq!(editor.text(), "bar");
+        press(&mut editor, KeyCode::Char('x'));
+        assert_eq!(editor.text(), "xbar");
+
+        press(&mut editor, KeyCode::Right);
+        ctrl(&mut editor, 'k');
+        assert_eq!(editor.text(), "xb");
+    }
+
+    #[test]
+    fn ignores_other_control_keys() {
+        let mut editor = LineEditor::new("abc");
+        assert!(!editor.handle_key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL)));
+        assert!(!editor.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(editor.text(), "abc");
+    }
+
+    #[test]
+    fn pastes_at_the_cursor_without_control_chars() {
+        let mut editor = LineEditor::new("ab");
+        press(&mut editor, KeyCode::Home);
+        editor.paste("x\ny\tz\r\n");
+        press(&mut editor, KeyCode::Char('-'));
+        assert_eq!(editor.text(), "xyz-ab");
+    }
+
+    #[test]
+    fn view_scrolls_to_keep_the_cursor_visible() {
+        let mut editor = LineEditor::new("abcdefgh");
+        assert_eq!(editor.view(4, false), ("fgh".to_string(), 3));
+
+        press(&mut editor, KeyCode::Home);
+        assert_eq!(editor.view(4, false), ("abcd".to_string(), 0));
+
+        for _ in 0..5 {
+            press(&mut editor, KeyCode::Right);
+        }
+        assert_eq!(editor.view(4, false), ("cdef".to_string(), 3));
+    }
+
+    #[test]
+    fn view_counts_wide_chars_as_two_columns() {
+        let editor = LineEditor::new("日本語");
+        assert_eq!(editor.view(5, false), ("本語".to_string(), 4));
+
+        let mut editor = LineEditor::new("a日b");
+        press(&mut editor, KeyCode::Left);
+        assert_eq!(editor.view(10, false), ("a日b".to_string(), 3));
+    }
+
+    #[test]
+    fn view_masks_every_char() {
+        let editor = LineEditor::new("sécret");
+        assert_eq!(editor.view(10, true), ("••••••".to_string(), 6));
+    }
+}
@@ -1,166 +1,491 @@
 use crate::app::{App, Focus};
 use crate::background::spawn_cache_updater;
-use crate::cache::Cache;
-use crate::config::Config;
-use anyhow::Result;
-use clap::Parser;
+use crate::cache::{Cache, CacheKey, CachedEntry};
+use crate::config::{Config, Profile};
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, TimeDelta, Utc};
+use clap::{Parser, Subcommand};
 use ratatui::{
     Terminal,
     backend::TestBackend,
     crossterm::{
-        event::{self, Event, KeyCode},
+        event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyModifiers},
         execute,
         terminal::{
             self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
         },
     },
     prelude::CrosstermBackend,
-    style::{Color, Modifier},
+    style::Modifier,
+};
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{io, process, sync::Arc, time::Duration};
 use tokio::{sync::Mutex, time::sleep};
 
 mod api;
 mod app;
 mod background;
 mod cache;
+mod cells;
+mod color;
 mod config;
+mod daemon;
+mod db;
+mod diff;
+mod duration;
+mod history;
+mod input;
 mod models;
+mod paths;
+mod query;
+mod scale;
+mod stats;
+mod storage;
+mod theme;
 mod ui;
 
 #[derive(Parser)]
 #[command(name = "trexanh")]
 #[command(about = "GitHub Contribution Graph TUI")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(long, hide = true)]
     update_cache: bool,
 
-    #[arg(long)]
+    #[arg(long, overrides_with = "no_cached")]
     cached: bool,
+    /// Fetch in the foreground even if `behavior.cached` is set in the config
+    #[arg(long, overrides_with = "cached")]
+    no_cached: bool,
+    /// With --cached, fetch in the foreground if the cache is older than this (e.g. 30m, 3h, 2d)
+    #[arg(long, value_parser = duration::parse)]
+    max_age: Option<TimeDelta>,
+    /// Render only from the cache, never touching the network
+    #[arg(long, conflicts_with_all = ["max_age", "reset"])]
+    offline: bool,
     #[arg(long)]
     width: Option<u16>,
-    #[arg(long)]
+    /// When to color the output: auto, always or never
+    #[arg(long, value_enum, global = true)]
+    color: Option<color::ColorChoice>,
+    /// How days are drawn: full, square, half, braille, or auto to pick
+    /// the widest that fits
+    #[arg(long, value_enum)]
+    cells: Option<cells::CellMode>,
+    /// How counts map to colors
+    #[arg(long, value_enum)]
+    scale: Option<scale::ScaleMode>,
+    /// Color theme: default, github-dark, github-light, classic, halloween,
+    /// colorblind or one defined under [themes] in the config
+    #[arg(long, global = true)]
+    theme: Option<String>,
+    /// Refresh every SECONDS, 0 turns off `behavior.watch` from the config
+    #[arg(long, value_name = "SECONDS")]
     watch: Option<u64>,
 
     #[arg(long)]
     reset: bool,
 
+    /// Use this config file instead of the default location
+    #[arg(long, global = true, env = "TREXANH_CONFIG")]
+    config: Option<PathBuf>,
+    /// Keep cache and history in this directory
+    #[arg(long, global = true, env = "TREXANH_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Use this named profile instead of the configured default
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     username: Option<String>,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Show how fetched contribution counts changed over time
+    History {
+        username: Option<String>,
+
+        /// Only show changes to this day (YYYY-MM-DD)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Include the initial snapshot of each user
+        #[arg(long)]
+        all: bool,
+    },
+    /// Aggregate stored daily counts by week, month, year or user
+    Query {
+        #[arg(long, value_enum, default_value = "month")]
+        by: query::Period,
+        /// Only include these users (repeatable)
+        #[arg(long = "user")]
+        users: Vec<String>,
+        /// First day to include (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Last day to include (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+    },
+    /// Inspect and manage cached contribution graphs
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Keep tracked users' graphs fresh and serve them to other trexanh runs
+    Daemon {
+        /// Refresh every user this often (default: daemon.interval from the config, else 15m)
+        #[arg(long, value_parser = duration::parse)]
+        interval: Option<TimeDelta>,
+    },
+    /// Show or change the config without going through --reset
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the config with tokens masked
+    Show,
+    /// Print one value, e.g. `display.width` or `profiles.work.username`
+    Get { key: String },
+    /// Change one value, e.g. `set behavior.watch 300`
+    Set { key: String, value: String },
+    /// Remove one value, falling back to its default
+    Unset { key: String },
+    /// Print the path of the config file
+    Path,
+    /// Open the config file in $VISUAL or $EDITOR
+    Edit,
+}
+
+fn run_config_command(action: ConfigCommand) -> Result<()> {
+    match action {
+        ConfigCommand::Show => config::print(),
+        ConfigCommand::Get { key } => config::print_key(&key),
+        ConfigCommand::Set { key, value } => config::set(&key, &value),
+        ConfigCommand::Unset { key } => config::unset(&key),
+        ConfigCommand::Path => {
+            println!("{}", Config::path()?.display());
+            Ok(())
+        }
+        ConfigCommand::Edit => config::edit(),
+    }
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// List every cached user with fetch time and size
+    List,
+    /// Show details of one cached user
+    Show { username: String },
+    /// Drop entries fetched longer ago than the given age (e.g. 30d)
+    Prune {
+        #[arg(long, value_parser = duration::parse)]
+        older_than: TimeDelta,
+    },
+    /// Remove one user, or every entry if no user is given
+    Clear { username: Option<String> },
+    /// Write the cache as JSON to a file, or stdout
+    Export { file: Option<PathBuf> },
+    /// Merge a cache exported elsewhere, keeping the newest entry per user
+    Import { file: Option<PathBuf> },
+}
+
+/// The profile subcommands work with, if a config exists. Naming a profile
+/// that isn't configured is an error.
+fn selected_profile(requested: Option<&str>) -> Result<Option<Profile>> {
+    if !Config::exists() {
+        return match requested {
+            Some(name) => anyhow::bail!("Profile {} not found, no config exists", name),
+            None => Ok(None),
+        };
+    }
+
+    let config = Config::load()?;
+    let name = config.profile_name(requested);
+
+    match config.profiles.get(&name) {
+        Some(profile) => Ok(Some(profile.clone())),
+        None if requested.is_some() => anyhow::bail!("Profile {} not found", name),
+        None => Ok(None),
+    }
+}
+
+fn run_cache_command(action: CacheCommand, host: &str) -> Result<()> {
+    match action {
+        CacheCommand::List => cache::print_list(),
+        CacheCommand::Show { username } => cache::print_entry(&CacheKey::new(host, &username)),
+        CacheCommand::Prune { older_than } => {
+            let removed = Cache::update(|cache| {
+                cache.print_warnings();
+                Ok(cache.prune(older_than))
+            })?;
+            println!("Removed {} entries", removed);
+            Ok(())
+        }
+        CacheCommand::Clear { username } => {
+            let removed = Cache::update(|cache| {
+                cache.print_warnings();
+                Ok(match &username {
+                    Some(username) => cache.remove(&CacheKey::new(host, username)) as usize,
+                    None => cache.clear(),
+                })
+            })?;
+            println!("Removed {} entries", removed);
+            Ok(())
+        }
+        CacheCommand::Export { file } => cache::export(file.as_deref()),
+        CacheCommand::Import { file } => {
+            let merged = cache::import(file.as_deref())?;
+            println!("Imported {} entries", merged);
+            Ok(())
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    paths::init(args.config.clone(), args.cache_dir.clone())?;
+
+    if let Some(command) = args.command {
+        let requested = args.profile.as_deref();
+
+        return match command {
+            Command::History {
+                username,
+                date,
+                all,
+            } => {
+                let profile = selected_profile(requested)?;
+                let host = profile.as_ref().map_or(api::GITHUB_HOST, Profile::host);
+                let username = username
+                    .or(profile.as_ref().map(|profile| profile.username.clone()))
+                    .context("No username given and no config found")?;
+                history::print(&username, host, date, all)
+            }
+            Command::Query {
+                by,
+                mut users,
+                since,
+                until,
+            } => {
+                if users.is_empty() && Config::exists() {
+                    users = Config::load()?.users.tracked;
+                }
+                query::print(by, &users, since, until)
+            }
+            Command::Cache { action } => {
+                let profile = selected_profile(requested)?;
+                let host = profile.as_ref().map_or(api::GITHUB_HOST, Profile::host);
+                run_cache_command(action, host)
+            }
+            Command::Daemon { interval } => {
+                let config =
+                    Config::load().context("No config found, run trexanh to set one up")?;
+                let profile = selected_profile(requested)?
+                    .context("No profile configured, run trexanh to set one up")?;
+                let interval = match interval {
+                    Some(interval) => interval,
+                    None => config.daemon.interval()?,
+                };
+                daemon::run(config, profile, interval).await
+            }
+            Command::Config { action } => run_config_command(action),
+        };
+    }
 
-    let mut config = if Config::exists() && !args.reset {
+    let mut config = if Config::exists() {
         Config::load()?
     } else {
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
-
-        let mut config = Config {
-            username: "".to_string(),
-            token: "".to_string(),
-        };
-        let mut app = App::new(config.clone());
-
-        loop {
-            terminal.draw(|frame| ui::render_input(frame, &app))?;
-
-            if event::poll(Duration::from_millis(200))?
-                && let Event::Key(key) = event::read()?
-            {
-                match key.code {
-                    KeyCode::Tab => {
-                        app.focus = match app.focus {
-                            Focus::Username => Focus::Token,
-                            Focus::Token => Focus::Username,
-                        };
-                    }
+        Config::default()
+    };
+    let profile_name = config.profile_name(args.profile.as_deref());
+    let support = color::Support::detect(args.color.or(config.display.color).unwrap_or_default());
+    let theme = theme::resolve(args.theme.as_deref(), &config)?.adapt(support);
+
+    let mut profile = match config.profiles.get(&profile_name) {
+        Some(profile) if !args.reset => profile.clone(),
+        _ if args.offline => Profile {
+            username: args
+                .username
+                .clone()
+                .context("No username given and no config found")?,
+            ..Profile::default()
+        },
+        _ if args.update_cache => {
+            anyhow::bail!("Profile {} not found", profile_name)
+        }
+        _ if !io::stdin().is_terminal() || !io::stdout().is_terminal() => {
+            anyhow::bail!("No profile configured, run trexanh in a terminal to set one up")
+        }
+        existing => {
+            enable_raw_mode()?;
+            let mut stdout = io::stdout();
+            execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+            let backend = CrosstermBackend::new(stdout);
+            let mut terminal = Terminal::new(backend)?;
+
+            let mut app = App::new(
+                config.clone(),
+                Profile {
+                    username: "".to_string(),
+                    token: "".to_string(),
+                    ..existing.cloned().unwrap_or_default()
+                },
+            );
+            app.theme = theme;
+
+            let profile = loop {
+                terminal.draw(|frame| ui::render_input(frame, &app))?;
+
+                if !event::poll(Duration::from_millis(200))? {
+                    continue;
+                }
 
-                    KeyCode::Enter => {
-                        config.username = app.config.username.trim().to_string();
-                        config.token = app.config.token.trim().to_string();
+                match event::read()? {
+                    Event::Paste(text) => app.focused_input().paste(&text),
+
+                    Event::Key(key) => match key.code {
+                        KeyCode::Tab | KeyCode::BackTab if app.asks_token() => {
+                            app.focus = match app.focus {
+                                Focus::Username => Focus::Token,
+                                Focus::Token => Focus::Username,
+                            };
+                        }
 
-                        if !config.username.is_empty() && !config.token.is_empty() {
-                            config.save()?;
-                            break;
+                        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.reveal_token = !app.reveal_token;
                         }
-                    }
 
-                    KeyCode::Char(c) => {
-                        let target = match app.focus {
-                            Focus::Username => &mut app.config.username,
-                            Focus::Token => &mut app.config.token,
-                        };
-                        target.push(c);
-                    }
+                        KeyCode::Enter => {
+                            let profile = Profile {
+                                username: app.username_input.text().trim().to_string(),
+                                token: app.token_input.text().trim().to_string(),
+                                ..app.profile.clone()
+                            };
+
+                            if !profile.username.is_empty()
+                                && (!profile.token.is_empty() || !app.requires_token())
+                            {
+                                if config.profiles.is_empty() {
+                                    config.default_profile = Some(profile_name.clone());
+                                }
+                                config
+                                    .profiles
+                                    .insert(profile_name.clone(), profile.clone());
+                                config.save()?;
+                                break profile;
+                            }
+                        }
 
-                    KeyCode::Backspace => {
-                        let target = match app.focus {
-                            Focus::Username => &mut app.config.username,
-                            Focus::Token => &mut app.config.token,
-                        };
-                        target.pop();
-                    }
+                        KeyCode::Esc => {
+                            disable_raw_mode()?;
+                            execute!(
+                                terminal.backend_mut(),
+                                LeaveAlternateScreen,
+                                DisableBracketedPaste
+                            )?;
+                            process::exit(0);
+                        }
 
-                    KeyCode::Esc => {
-                        disable_raw_mode()?;
-                        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                        process::exit(0);
-                    }
+                        _ => {
+                            app.focused_input().handle_key(key);
+                        }
+                    },
 
                     _ => {}
                 }
-            }
-        }
+            };
+
+            disable_raw_mode()?;
+            execute!(
+                terminal.backend_mut(),
+                LeaveAlternateScreen,
+                DisableBracketedPaste
+            )?;
 
-        config
+            profile
+        }
     };
 
-    config.username = args
+    profile.username = args
         .username
         .as_deref()
-        .unwrap_or(&config.username)
+        .unwrap_or(&profile.username)
         .to_string();
 
+    // Command-line flags take precedence over the config.
+    let cached = (args.cached || config.behavior.cached) && !args.no_cached;
+    let max_age = match args.max_age {
+        Some(max_age) => Some(max_age),
+        None => config.behavior.max_age()?,
+    };
+    let watch = args
+        .watch
+        .or(config.behavior.watch)
+        .filter(|&secs| secs > 0);
+    let width = args.width.or(config.display.width);
+    let min_update_age = config.behavior.min_update_age()?;
+
     if args.update_cache {
-        let mut app = App::new(config.clone());
-        app.load().await?;
-        if let Some(calendar) = app.calendar {
-            let mut cache = Cache::load()?;
-            cache.insert(config.username, calendar);
-            cache.save()?;
-        }
-        return Ok(());
+        let app = App::new(config.clone(), profile.clone());
+        return background::update_cache(app, min_update_age).await;
     }
 
-    let mut app = App::new(config.clone());
-    let mut cache = Cache::load()?;
+    let mut app = App::new(config.clone(), profile.clone());
+    app.theme = theme;
+    app.scaling = scale::Scaling::new(
+        args.scale.or(config.display.scale).unwrap_or_default(),
+        config.display.breakpoints.as_deref(),
+    )?;
+    app.cells = args.cells.or(config.display.cells).unwrap_or_default();
+    app.watching = watch.is_some();
+    app.offline = args.offline;
+    let cache = Cache::load()?;
+    app.warnings = cache.warnings().to_vec();
+    let cache_key = app.cache_key();
+
+    let is_fresh = |entry: &CachedEntry| {
+        entry.has_calendar()
+            && max_age.is_none_or(|max_age| Utc::now() - entry.fetched_at <= max_age)
+    };
+    let fresh_entry = cache.get(&cache_key).filter(|entry| is_fresh(entry));
 
-    if args.cached {
-        if let Some(calendar) = cache.get(&config.username) {
-            app.calendar = Some(calendar);
-            spawn_cache_updater(&config.username)?;
-        } else {
-            app.load().await?;
-            if let Some(ref calendar) = app.calendar {
-                cache.insert(config.username, calendar.clone());
-                cache.save()?;
+    if args.offline {
+        app.load().await?;
+        if let Some(entry) = cache.get(&cache_key) {
+            app.set_changes(entry.changes.clone());
+        }
+    } else if cached {
+        // A running daemon keeps the cache warm, so there's nothing to spawn.
+        if let Some(entry) = daemon::request(&cache_key).filter(is_fresh) {
+            app.load_cached(&entry);
+        } else if let Some(entry) = fresh_entry {
+            app.load_cached(entry);
+            if Utc::now() - entry.fetched_at >= min_update_age {
+                spawn_cache_updater(&profile_name, &profile.username, &cache_key)?;
             }
+        } else {
+            let entry = app.refresh_cache().await?;
+            app.set_changes(entry.changes);
         }
     } else {
-        app.load().await?;
-        if let Some(ref calendar) = app.calendar {
-            cache.insert(config.username, calendar.clone());
-            cache.save()?;
-        }
+        let entry = app.refresh_cache().await?;
+        app.set_changes(entry.changes);
     }
 
-    if let Some(watch_secs) = args.watch {
+    if let Some(watch_secs) = watch {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen)?;
@@ -194,11 +519,37 @@ async fn main() -> Result<()> {
             }
         });
 
+        let mut expired_highlight = None;
+
         loop {
             if event::poll(Duration::from_millis(200))? {
                 match event::read()? {
                     Event::Key(key) if key.code == KeyCode::Char('q') => break,
 
+                    Event::Key(key) => {
+                        let mut term = terminal.lock().await;
+                        let mut app = app.lock().await;
+                        let page = ui::visible_weeks(&app, term.size()?.width);
+                        let weeks = app.calendar.as_ref().map_or(0, |c| c.weeks.len()) as isize;
+
+                        let moved = match key.code {
+                            KeyCode::Left | KeyCode::Char('h') => app.step(-1, page),
+                            KeyCode::Right | KeyCode::Char('l') => app.step(1, page),
+                            KeyCode::Up | KeyCode::Char('k') => app.move_cursor(0, -1, page),
+                            KeyCode::Down | KeyCode::Char('j') => app.move_cursor(0, 1, page),
+                            KeyCode::PageUp => app.scroll(-(page as isize), page),
+                            KeyCode::PageDown => app.scroll(page as isize, page),
+                            KeyCode::Home => app.scroll(-weeks, page),
+                            KeyCode::End => app.scroll(weeks, page),
+                            KeyCode::Esc => app.cursor.take().is_some(),
+                            _ => false,
+                        };
+
+                        if moved {
+                            term.draw(|f| ui::render(f, &app))?;
+                        }
+                    }
+
                     Event::Resize(_, _) => {
                         let mut term = terminal.lock().await;
                         let app = app.lock().await;
@@ -209,6 +560,18 @@ async fn main() -> Result<()> {
                     }
                     _ => {}
                 }
+            } else {
+                let app = app.lock().await;
+
+                if let Some(until) = app.highlight_until
+                    && Instant::now() >= until
+                    && expired_highlight != Some(until)
+                {
+                    expired_highlight = Some(until);
+
+                    let mut term = terminal.lock().await;
+                    term.draw(|f| ui::render(f, &app))?;
+                }
             }
         }
 
@@ -216,9 +579,9 @@ async fn main() -> Result<()> {
         disable_raw_mode()?;
     } else {
         let (term_cols, _) = terminal::size().unwrap_or((80, 24));
-        let cols = args.width.unwrap_or(term_cols);
+        let cols = width.unwrap_or(term_cols);
 
-        let height = 12;
+        let height = ui::required_height(&app, cols).max(config.display.height.unwrap_or(0));
 
         let backend = TestBackend::new(cols, height);
         let mut terminal = Terminal::new(backend)?;
@@ -229,34 +592,43 @@ async fn main() -> Result<()> {
 
         for y in 0..height {
             let mut line = String::new();
-            let mut last_fg = None;
-            let mut last_bold = false;
+            let mut last_style = (None, None, false);
 
             for x in 0..cols {
                 let cell = &buffer[(x, y)];
-                let current_fg = match cell.fg {
-                    Color::Rgb(r, g, b) => Some((r, g, b)),
-                    _ => None,
-                };
-                let current_bold = cell.modifier.contains(Modifier::BOLD);
 
-                if current_fg != last_fg || current_bold != last_bold {
-                    line.push_str("\x1b[0m");
+                if support != color::Support::None {
+                    let style = (
+                        color::foreground(cell.fg),
+                        color::background(cell.bg),
+                        cell.modifier.contains(Modifier::BOLD),
+                    );
 
-                    if let Some((r, g, b)) = current_fg {
-                        line.push_str(&format!("\x1b[38;2;{};{};{}m", r, g, b));
-                    }
-                    if current_bold {
-                        line.push_str("\x1b[1m");
-                    }
+                    if style != last_style {
+                        line.push_str("\x1b[0m");
 
-                    last_fg = current_fg;
-                    last_bold = current_bold;
+                        let (fg, bg, bold) = &style;
+                        if let Some(fg) = fg {
+                            line.push_str(&format!("\x1b[{}m", fg));
+                        }
+                        if let Some(bg) = bg {
+                            line.push_str(&format!("\x1b[{}m", bg));
+                        }
+                        if *bold {
+                            line.push_str("\x1b[1m");
+                        }
+
+                        last_style = style;
+                    }
                 }
 
                 line.push_str(cell.symbol());
             }
 
+            if support == color::Support::None {
+                println!("{}", line.trim_end());
+                continue;
+            }
             println!("{}\x1b[0m", line.trim_end());
         }
     }
@@ -0,0 +1,124 @@
+use anyhow::{Result, anyhow};
+use std::{
+    env, fs,
+    path::{self, Path, PathBuf},
+    sync::OnceLock,
+};
+
+#[derive(Default)]
+pub struct Overrides {
+    pub config: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+}
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+const LEGACY_CACHE_FILES: [&str; 2] = ["cache.json", "history.jsonl"];
+
+/// Records `--config` / `--cache-dir` and migrates files from the legacy
+/// `~/.trexanh/` directory. Paths are made absolute because the background
+/// updater runs from `/`.
+pub fn init(config: Option<PathBuf>, cache_dir: Option<PathBuf>) -> Result<()> {
+    let overrides = Overrides {
+        config: config.map(path::absolute).transpose()?,
+        cache_dir: cache_dir.map(path::absolute).transpose()?,
+    };
+
+    let _ = OVERRIDES.set(overrides);
+
+    let _ = config_file();
+    for name in LEGACY_CACHE_FILES {
+        let _ = cache_file(name);
+    }
+
+    Ok(())
+}
+
+pub fn overrides() -> &'static Overrides {
+    OVERRIDES.get_or_init(Overrides::default)
+}
+
+fn base_dir(xdg_var: &str, home_fallback: &str) -> Result<PathBuf> {
+    if let Some(dir) = env::var_os(xdg_var).filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let home = env::var_os("HOME")
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| anyhow!("Neither {} nor HOME environment variable is set", xdg_var))?;
+
+    Ok(PathBuf::from(home).join(home_fallback))
+}
+
+fn legacy_file(name: &str) -> Option<PathBuf> {
+    let home = env::var_os("HOME").filter(|v| !v.is_empty())?;
+    Some(PathBuf::from(home).join(".trexanh").join(name))
+}
+
+/// Moves `name` from the legacy `~/.trexanh/` directory to `path`. If that
+/// fails, the legacy file keeps being used.
+fn with_legacy_fallback(name: &str, path: PathBuf) -> PathBuf {
+    let Some(legacy) = legacy_file(name) else {
+        return path;
+    };
+    if path.exists() || !legacy.exists() {
+        return path;
+    }
+
+    match migrate(&legacy, &path) {
+        Ok(()) => path,
+        Err(_) => legacy,
+    }
+}
+
+fn migrate(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+
+    if let Some(legacy_dir) = from.parent() {
+        let _ = fs::remove_dir(legacy_dir);
+    }
+
+    Ok(())
+}
+
+pub fn config_file() -> Result<PathBuf> {
+    if let Some(path) = &overrides().config {
+        return Ok(path.clone());
+    }
+
+    let dir = base_dir("XDG_CONFIG_HOME", ".config")?.join("trexanh");
+    let toml = dir.join("config.toml");
+    let json = with_legacy_fallback("config.json", dir.join("config.json"));
+
+    // A JSON config from an older version keeps being used until replaced.
+    Ok(if !toml.exists() && json.exists() {
+        json
+    } else {
+        toml
+    })
+}
+
+pub fn cache_dir() -> Result<PathBuf> {
+    if let Some(dir) = &overrides().cache_dir {
+        return Ok(dir.clone());
+    }
+
+    Ok(base_dir("XDG_CACHE_HOME", ".cache")?.join("trexanh"))
+}
+
+pub fn cache_file(name: &str) -> Result<PathBuf> {
+    let path = cache_dir()?.join(name);
+
+    if overrides().cache_dir.is_some() {
+        return Ok(path);
+    }
+
+    Ok(with_legacy_fallback(name, path))
+}
@@ -0,0 +1,91 @@
+use crate::db::{Aggregate, Db};
+use anyhow::Result;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+    /// One total per user over the whole range
+    User,
+}
+
+impl Period {
+    fn strftime(self) -> Option<&'static str> {
+        match self {
+            Period::Week => Some("%Y-W%W"),
+            Period::Month => Some("%Y-%m"),
+            Period::Year => Some("%Y"),
+            Period::User => None,
+        }
+    }
+}
+
+pub fn print(
+    by: Period,
+    users: &[String],
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+) -> Result<()> {
+    let since = since.map(|date| date.format("%Y-%m-%d").to_string());
+    let until = until.map(|date| date.format("%Y-%m-%d").to_string());
+
+    let rows = Db::open()?.aggregate(by.strftime(), users, since.as_deref(), until.as_deref())?;
+
+    if rows.is_empty() {
+        println!("No stored contributions match");
+        return Ok(());
+    }
+
+    // The same login on another host is someone else, so it gets the host.
+    let several_hosts = rows.iter().any(|row| row.host != rows[0].host);
+    let name = |row: &Aggregate| {
+        if several_hosts {
+            format!("{}/{}", row.host, row.user)
+        } else {
+            row.user.clone()
+        }
+    };
+
+    let user_width = rows
+        .iter()
+        .map(|row| name(row).len())
+        .max()
+        .unwrap_or(0)
+        .max("USER".len());
+
+    match by.strftime() {
+        Some(_) => println!(
+            "{:<8}  {:<user_width$}  {:>6}  {:>6}  {:>4}",
+            "PERIOD", "USER", "TOTAL", "ACTIVE", "BEST"
+        ),
+        None => println!(
+            "{:<user_width$}  {:>6}  {:>6}  {:>4}",
+            "USER", "TOTAL", "ACTIVE", "BEST"
+        ),
+    }
+
+    for row in rows {
+        match by.strftime() {
+            Some(_) => println!(
+                "{:<8}  {:<user_width$}  {:>6}  {:>6}  {:>4}",
+                row.period,
+                name(&row),
+                row.total,
+                row.active_days,
+                row.best_day
+            ),
+            None => println!(
+                "{:<user_width$}  {:>6}  {:>6}  {:>4}",
+                name(&row),
+                row.total,
+                row.active_days,
+                row.best_day
+            ),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use crate::models::ContributionCalendar;
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// How contribution counts are mapped to the five color levels.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleMode {
+    /// 1-3, 4-6, 7-10 and 11+
+    #[default]
+    Fixed,
+    /// Quartiles of the days with contributions, like GitHub
+    Quartiles,
+    /// Evenly spaced on a log scale up to the busiest day
+    Log,
+    /// The four lower bounds in `display.breakpoints`
+    Breakpoints,
+}
+
+const FIXED: [u32; 4] = [1, 4, 7, 11];
+
+#[derive(Clone, Copy, Default)]
+pub enum Scaling {
+    #[default]
+    Fixed,
+    Quartiles,
+    Log,
+    Breakpoints([u32; 4]),
+}
+
+impl Scaling {
+    pub fn new(mode: ScaleMode, breakpoints: Option<&[u32]>) -> Result<Self> {
+        Ok(match mode {
+            ScaleMode::Fixed => Self::Fixed,
+            ScaleMode::Quartiles => Self::Quartiles,
+            ScaleMode::Log => Self::Log,
+            ScaleMode::Breakpoints => {
+                let Some(&[a, b, c, d]) = breakpoints else {
+                    bail!("The breakpoints scale needs display.breakpoints = [a, b, c, d]");
+                };
+                if a == 0 || !(a < b && b < c && c < d) {
+                    bail!(
+                        "display.breakpoints must be four increasing counts starting at 1 or more"
+                    );
+                }
+                Self::Breakpoints([a, b, c, d])
+            }
+        })
+    }
+
+    /// The lowest count of levels 1 to 4 for this calendar.
+    pub fn thresholds(&self, calendar: &ContributionCalendar) -> [u32; 4] {
+        let mut counts: Vec<u32> = calendar
+            .weeks
+            .iter()
+            .flat_map(|week| &week.contribution_days)
+            .map(|day| day.contribution_count)
+            .filter(|&count| count > 0)
+            .collect();
+        counts.sort_unstable();
+
+        let thresholds = match self {
+            Self::Fixed => FIXED,
+            Self::Breakpoints(breakpoints) => *breakpoints,
+            // A year without contributions has nothing to scale to.
+            _ if counts.is_empty() => FIXED,
+            Self::Quartiles => {
+                let quartile = |q: usize| counts[(counts.len() - 1) * q / 4] + 1;
+                [1, quartile(1), quartile(2), quartile(3)]
+            }
+            Self::Log => {
+                let max = counts[counts.len() - 1];
+                let top = (max as f64 + 1.0).ln();
+                let bound = |k: u32| ((top * k as f64 / 4.0).exp() - 1.0).floor() as u32 + 1;
+                [1, bound(1), bound(2), bound(3)]
+            }
+        };
+
+        strictly_increasing(thresholds)
+    }
+}
+
+fn strictly_increasing(mut thresholds: [u32; 4]) -> [u32; 4] {
+    thresholds[0] = thresholds[0].max(1);
+    for i in 1..thresholds.len() {
+        thresholds[i] = thresholds[i].max(thresholds[i - 1] + 1);
+    }
+    thresholds
+}
+
+pub fn level(thresholds: &[u32; 4], count: u32) -> usize {
+    thresholds
+        .iter()
+        .rposition(|&threshold| count >= threshold)
+        .map_or(0, |i| i + 1)
+}
+
+/// Count range of each level for the legend, e.g. `0`, `1-3`, ..., `11+`.
+/// Level 0 also covers the counts below the first threshold.
+pub fn ranges(thresholds: &[u32; 4]) -> [String; 5] {
+    let range = |from: u32, to: u32| {
+        if from == to {
+            from.to_string()
+        } else {
+            format!("{}-{}", from, to)
+        }
+    };
+
+    [
+        range(0, thresholds[0].saturating_sub(1)),
+        range(thresholds[0], thresholds[1] - 1),
+        range(thresholds[1], thresholds[2] - 1),
+        range(thresholds[2], thresholds[3] - 1),
+        format!("{}+", thresholds[3]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContributionDay, Week};
+
+    fn calendar(counts: &[u32]) -> ContributionCalendar {
+        ContributionCalendar {
+            weeks: counts
+                .chunks(7)
+                .map(|days| Week {
+                    contribution_days: days
+                        .iter()
+                        .map(|&count| ContributionDay {
+                            date: String::new(),
+                            contribution_count: count,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn quartiles_of_an_empty_year_fall_back_to_fixed() {
+        let year = calendar(&[0; 365]);
+
+        assert_eq!(Scaling::Quartiles.thresholds(&year), FIXED);
+        assert_eq!(Scaling::Log.thresholds(&year), FIXED);
+        assert_eq!(Scaling::Quartiles.thresholds(&calendar(&[])), FIXED);
+    }
+
+    #[test]
+    fn quartiles_split_the_active_days() {
+        let year = calendar(&[0, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert_eq!(Scaling::Quartiles.thresholds(&year), [1, 3, 5, 7]);
+    }
+
+    #[test]
+    fn quartiles_of_equal_counts_stay_increasing() {
+        let year = calendar(&[0, 1, 1, 1, 1]);
+
+        assert_eq!(Scaling::Quartiles.thresholds(&year), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn log_scale_with_a_busiest_day_of_one() {
+        let year = calendar(&[0, 1, 0, 1]);
+        let thresholds = Scaling::Log.thresholds(&year);
+
+        assert_eq!(thresholds, [1, 2, 3, 4]);
+        assert_eq!(level(&thresholds, 0), 0);
+        assert_eq!(level(&thresholds, 1), 1);
+    }
+
+    #[test]
+    fn log_scale_reaches_the_busiest_day() {
+        let thresholds = Scaling::Log.thresholds(&calendar(&[1, 100]));
+
+        assert_eq!(thresholds, [1, 3, 10, 31]);
+        assert_eq!(level(&thresholds, 100), 4);
+    }
+
+    #[test]
+    fn breakpoints_apply_to_an_empty_year() {
+        let scaling = Scaling::new(ScaleMode::Breakpoints, Some(&[2, 5, 10, 20])).unwrap();
+
+        assert_eq!(scaling.thresholds(&calendar(&[0; 7])), [2, 5, 10, 20]);
+    }
+
+    #[test]
+    fn rejects_invalid_breakpoints() {
+        for breakpoints in [&[0, 1, 2, 3][..], &[1, 1, 2, 3], &[1, 2, 3]] {
+            assert!(Scaling::new(ScaleMode::Breakpoints, Some(breakpoints)).is_err());
+        }
+        assert!(Scaling::new(ScaleMode::Breakpoints, None).is_err());
+    }
+
+    #[test]
+    fn ranges_label_every_level() {
+        assert_eq!(ranges(&FIXED), ["0", "1-3", "4-6", "7-10", "11+"]);
+        assert_eq!(ranges(&[1, 2, 3, 4]), ["0", "1", "2", "3", "4+"]);
+        assert_eq!(
+            ranges(&[3, 5, 10, 20]),
+            ["0-2", "3-4", "5-9", "10-19", "20+"]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::models::{ContributionCalendar, ContributionDay, Week};
+use chrono::{Local, NaiveDate};
+
+pub fn today_date() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+pub fn find_day(calendar: &ContributionCalendar, date: NaiveDate) -> Option<(usize, usize)> {
+    let date = date.format("%Y-%m-%d").to_string();
+
+    calendar
+        .weeks
+        .iter()
+        .enumerate()
+        .find_map(|(week_idx, week)| {
+            week.contribution_days
+                .iter()
+                .position(|day| day.date == date)
+                .map(|day_idx| (week_idx, day_idx))
+        })
+}
+
+pub fn today(calendar: &ContributionCalendar) -> Option<(&Week, &ContributionDay)> {
+    let (week_idx, day_idx) = match find_day(calendar, today_date()) {
+        Some(position) => position,
+        None => {
+            let week_idx = calendar.weeks.len().checked_sub(1)?;
+            let day_idx = calendar.weeks[week_idx]
+                .contribution_days
+                .len()
+                .checked_sub(1)?;
+            (week_idx, day_idx)
+        }
+    };
+
+    let week = &calendar.weeks[week_idx];
+    Some((week, &week.contribution_days[day_idx]))
+}
+
+/// Rank of a day with `count` contributions among all days in the calendar,
+/// 1 being the busiest, and the number of days. Ties share a rank.
+pub fn rank(calendar: &ContributionCalendar, count: u32) -> (usize, usize) {
+    let days = calendar
+        .weeks
+        .iter()
+        .flat_map(|week| &week.contribution_days);
+    let busier = days
+        .clone()
+        .filter(|day| day.contribution_count > count)
+        .count();
+
+    (busier + 1, days.count())
+}
+
+pub fn week_total(week: &Week) -> u32 {
+    week.contribution_days
+        .iter()
+        .map(|day| day.contribution_count)
+        .sum()
+}
+
+/// Number of consecutive days before today with at least `min_count` contributions.
+pub fn streak_before_today(calendar: &ContributionCalendar, min_count: u32) -> u32 {
+    let today = today_date().format("%Y-%m-%d").to_string();
+
+    calendar
+        .weeks
+        .iter()
+        .flat_map(|week| week.contribution_days.iter())
+        .rev()
+        .skip_while(|day| day.date >= today)
+        .take_while(|day| day.contribution_count >= min_count.max(1))
+        .count() as u32
+}
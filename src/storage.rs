@@ -0,0 +1,106 @@
+use anyhow::{Context, Result, anyhow};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    os::{
+        fd::AsRawFd,
+        unix::fs::{FileExt, OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+    process,
+};
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so
+/// readers never observe a partially written file.
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    write_atomic_with_mode(path, contents, 0o666)
+}
+
+/// Like [`write_atomic`], but the file is only readable by its owner.
+pub fn write_private(path: &Path, contents: &str) -> Result<()> {
+    write_atomic_with_mode(path, contents, 0o600)
+}
+
+fn write_atomic_with_mode(path: &Path, contents: &str, mode: u32) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+
+    let tmp_path = with_suffix(path, &format!(".{}.tmp", process::id()));
+    let _ = fs::remove_file(&tmp_path);
+
+    let result = (|| -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .open(&tmp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Advisory `flock` on `<path>.lock`, released on drop.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Takes the lock, or returns `None` instead of waiting if another
+    /// process holds it.
+    pub fn try_exclusive(path: &Path) -> Result<Option<Self>> {
+        let lock_path = with_suffix(path, ".lock");
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == -1 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::WouldBlock {
+                return Ok(None);
+            }
+            return Err(anyhow!("Failed to lock {}: {}", lock_path.display(), err));
+        }
+
+        Ok(Some(Self { file }))
+    }
+
+    /// Records the holder's PID in the lock file, for anyone wondering which
+    /// process it is.
+    pub fn write_pid(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file
+            .write_all_at(process::id().to_string().as_bytes(), 0)?;
+        Ok(())
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use crate::color::{self, Support};
+use crate::config::{Config, HexColor, ThemeConfig};
+use anyhow::{Result, bail};
+use ratatui::style::{Color, Modifier, Style};
+
+#[derive(Clone, Copy)]
+pub struct Theme {
+    /// Cell colors from no contributions to the most.
+    pub levels: [Color; 5],
+    /// Levels drawn in bold, where the terminal has too few colors to tell
+    /// them apart otherwise.
+    pub bold_levels: [bool; 5],
+    /// Drawn for each level instead of blocks when there are no colors.
+    pub glyphs: Option<[&'static str; 5]>,
+    /// Labels, title and legend.
+    pub text: Color,
+    pub border: Color,
+    /// Unfocused inputs on the setup screen.
+    pub muted: Color,
+    pub error: Color,
+    /// Days that meet the contribution goal.
+    pub goal: Color,
+    /// Days that changed since the last fetch.
+    pub changed: Color,
+}
+
+/// One glyph per level for terminals without colors, from no contributions
+/// to the most.
+const GLYPHS: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+
+impl Theme {
+    /// Maps every color to the closest one the terminal supports. The levels
+    /// get ordered basic colors on 16 color terminals and glyphs without colors.
+    pub fn adapt(self, support: Support) -> Self {
+        let mut theme = Self {
+            levels: self.levels.map(|color| support.adapt(color)),
+            bold_levels: self.bold_levels,
+            glyphs: self.glyphs,
+            text: support.adapt(self.text),
+            border: support.adapt(self.border),
+            muted: support.adapt(self.muted),
+            error: support.adapt(self.error),
+            goal: support.adapt(self.goal),
+            changed: support.adapt(self.changed),
+        };
+
+        match support {
+            Support::None => theme.glyphs = Some(GLYPHS),
+            Support::Ansi16 => {
+                let levels = color::ansi16_levels(&self.levels);
+                theme.levels = levels.map(|(color, _)| color);
+                theme.bold_levels = levels.map(|(_, bold)| bold);
+            }
+            Support::Ansi256 | Support::TrueColor => {}
+        }
+
+        theme
+    }
+
+    pub fn level_style(&self, level: usize) -> Style {
+        let style = Style::default().fg(self.levels[level]);
+        if self.bold_levels[level] {
+            style.add_modifier(Modifier::BOLD)
+        } else {
+            style
+        }
+    }
+
+    /// The glyph for `level`, or `block` when the levels have colors.
+    pub fn level_symbol(&self, level: usize, block: &'static str) -> &'static str {
+        self.glyphs.map_or(block, |glyphs| glyphs[level])
+    }
+
+    /// Whether the levels differ by their color alone, so they can also be
+    /// told apart as backgrounds.
+    pub fn levels_by_color(&self) -> bool {
+        self.glyphs.is_none() && !self.bold_levels.contains(&true)
+    }
+}
+
+const fn rgb(hex: u32) -> Color {
+    Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            levels: [
+                rgb(0x373737),
+                rgb(0x5a8c78),
+                rgb(0x78b482),
+                rgb(0xa0d296),
+                rgb(0xdcf0aa),
+            ],
+            bold_levels: [false; 5],
+            glyphs: None,
+            text: rgb(0xffffff),
+            border: rgb(0xffffff),
+            muted: rgb(0x7f7f7f),
+            error: rgb(0xcd0000),
+            goal: rgb(0xffc83c),
+            changed: rgb(0xffffff),
+        }
+    }
+}
+
+const BUILTIN_NAMES: [&str; 6] = [
+    "default",
+    "github-dark",
+    "github-light",
+    "classic",
+    "halloween",
+    "colorblind",
+];
+
+pub fn builtin(name: &str) -> Option<Theme> {
+    let default = Theme::default();
+
+    let theme = match name {
+        "default" => default,
+        "github-dark" => Theme {
+            levels: [
+                rgb(0x161b22),
+                rgb(0x0e4429),
+                rgb(0x006d32),
+                rgb(0x26a641),
+                rgb(0x39d353),
+            ],
+            text: rgb(0xc9d1d9),
+            border: rgb(0x30363d),
+            muted: rgb(0x8b949e),
+            error: rgb(0xf85149),
+            goal: rgb(0xd29922),
+            changed: rgb(0x58a6ff),
+            ..default
+        },
+        "github-light" => Theme {
+            levels: [
+                rgb(0xebedf0),
+                rgb(0x9be9a8),
+                rgb(0x40c463),
+                rgb(0x30a14e),
+                rgb(0x216e39),
+            ],
+            text: rgb(0x24292f),
+            border: rgb(0xd0d7de),
+            muted: rgb(0x57606a),
+            error: rgb(0xcf222e),
+            goal: rgb(0xbf8700),
+            changed: rgb(0x0969da),
+            ..default
+        },
+        "classic" => Theme {
+            levels: [
+                rgb(0xeeeeee),
+                rgb(0xd6e685),
+                rgb(0x8cc665),
+                rgb(0x44a340),
+                rgb(0x1e6823),
+            ],
+            ..default
+        },
+        "halloween" => Theme {
+            levels: [
+                rgb(0x161b22),
+                rgb(0x631c03),
+                rgb(0xbd561d),
+                rgb(0xfa7a18),
+                rgb(0xfddf68),
+            ],
+            goal: rgb(0x9b59d0),
+            ..default
+        },
+        // Blue to orange, distinguishable with the common color vision deficiencies.
+        "colorblind" => Theme {
+            levels: [
+                rgb(0x161b22),
+                rgb(0x1f4e99),
+                rgb(0x58a6ff),
+                rgb(0xf0883e),
+                rgb(0xffb77c),
+            ],
+            goal: rgb(0xffffff),
+            changed: rgb(0xd2a8ff),
+            ..default
+        },
+        _ => return None,
+    };
+
+    Some(theme)
+}
+
+impl From<HexColor> for Color {
+    fn from(HexColor(r, g, b): HexColor) -> Self {
+        Color::Rgb(r, g, b)
+    }
+}
+
+impl ThemeConfig {
+    fn apply(&self, base: Theme) -> Theme {
+        let pick = |color: Option<HexColor>, fallback: Color| color.map_or(fallback, Color::from);
+
+        Theme {
+            levels: self
+                .levels
+                .map_or(base.levels, |levels| levels.map(Color::from)),
+            text: pick(self.text, base.text),
+            border: pick(self.border, base.border),
+            muted: pick(self.muted, base.muted),
+            error: pick(self.error, base.error),
+            goal: pick(self.goal, base.goal),
+            changed: pick(self.changed, base.changed),
+            ..base
+        }
+    }
+}
+
+/// Resolves the `--theme` flag, else `display.theme`, against the palettes
+/// in the config and the built-in themes. `display.colors` goes on top
+/// unless the flag picked a theme.
+pub fn resolve(flag: Option<&str>, config: &Config) -> Result<Theme> {
+    let name = flag
+        .or(config.display.theme.as_deref())
+        .unwrap_or("default");
+
+    let mut theme = match config.themes.get(name) {
+        Some(custom) => {
+            let base_name = custom.base.as_deref().unwrap_or("default");
+            let Some(base) = builtin(base_name) else {
+                bail!("Theme {} is based on unknown theme {}", name, base_name);
+            };
+            custom.apply(base)
+        }
+        None => match builtin(name) {
+            Some(theme) => theme,
+            None => {
+                let mut names: Vec<&str> = BUILTIN_NAMES.to_vec();
+                names.extend(config.themes.keys().map(String::as_str));
+                bail!(
+                    "Unknown theme {}, expected one of: {}",
+                    name,
+                    names.join(", ")
+                );
+            }
+        },
+    };
+
+    if let Some(colors) = config.display.colors
+        && flag.is_none()
+    {
+        theme.levels = colors.map(Color::from);
+    }
+
+    Ok(theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HexColor;
+    use std::collections::HashSet;
+
+    fn config_with_colors() -> Config {
+        let mut config = Config::default();
+        config.display.theme = Some("halloween".to_string());
+        config.display.colors = Some([HexColor(1, 2, 3); 5]);
+        config
+    }
+
+    #[test]
+    fn config_colors_replace_the_configured_theme() {
+        let theme = resolve(None, &config_with_colors()).unwrap();
+
+        assert_eq!(theme.levels, [Color::Rgb(1, 2, 3); 5]);
+    }
+
+    #[test]
+    fn theme_flag_wins_over_config_colors() {
+        let theme = resolve(Some("classic"), &config_with_colors()).unwrap();
+
+        assert_eq!(theme.levels, builtin("classic").unwrap().levels);
+    }
+
+    #[test]
+    fn without_colors_levels_get_glyphs() {
+        let theme = Theme::default().adapt(Support::None);
+
+        assert_eq!(theme.levels, [Color::Reset; 5]);
+        assert_eq!(theme.level_symbol(0, "█"), "·");
+        assert_eq!(theme.level_symbol(4, "█"), "█");
+        assert!(!theme.levels_by_color());
+    }
+
+    #[test]
+    fn sixteen_colors_keep_the_levels_apart() {
+        let theme = Theme::default().adapt(Support::Ansi16);
+        let styles: HashSet<Style> = (0..5).map(|level| theme.level_style(level)).collect();
+
+        assert_eq!(styles.len(), 5);
+        assert_eq!(theme.level_symbol(2, "█"), "█");
+        assert!(Theme::default().adapt(Support::Ansi256).levels_by_color());
+    }
+
+    #[test]
+    fn unknown_themes_are_errors() {
+        assert!(resolve(Some("nope"), &Config::default()).is_err());
+    }
+}
@@ -1,5 +1,12 @@
 use crate::app::{App, Focus};
-use chrono::{Datelike, Duration, NaiveDate};
+use crate::cells::Cells;
+use crate::diff;
+use crate::duration;
+use crate::models::{ContributionCalendar, ContributionDay, Week};
+use crate::scale;
+use crate::stats;
+use crate::theme::Theme;
+use chrono::{Datelike, Duration, Local, NaiveDate, Timelike};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -8,27 +15,27 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Paragraph, Wrap},
 };
 
-fn render_error(frame: &mut Frame, area: Rect, min_width: u16, min_height: u16) {
+fn render_error(frame: &mut Frame, theme: &Theme, area: Rect, min_width: u16, min_height: u16) {
     let error_message = vec![
         Line::from(Span::styled(
             "Terminal too small!",
             Style::default()
-                .fg(Color::Rgb(205, 0, 0))
+                .fg(theme.error)
                 .add_modifier(Modifier::BOLD),
         )),
         Line::from(""),
         Line::from(Span::styled(
             format!("Minimum size: {}x{}", min_width, min_height),
-            Style::default().fg(Color::Rgb(255, 255, 255)),
+            Style::default().fg(theme.text),
         )),
         Line::from(Span::styled(
             format!("Current size: {}x{}", area.width, area.height),
-            Style::default().fg(Color::Rgb(255, 255, 255)),
+            Style::default().fg(theme.text),
         )),
     ];
 
     let paragraph = Paragraph::new(error_message)
-        .style(Style::default().fg(Color::Rgb(255, 255, 255)))
+        .style(Style::default().fg(theme.text))
         .alignment(Alignment::Center)
         .wrap(Wrap { trim: false });
 
@@ -53,7 +60,7 @@ pub fn render_input(frame: &mut Frame, app: &App) {
     let area = frame.area();
 
     if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
-        render_error(frame, area, MIN_WIDTH, MIN_HEIGHT);
+        render_error(frame, &app.theme, area, MIN_WIDTH, MIN_HEIGHT);
         return;
     }
 
@@ -86,23 +93,15 @@ pub fn render_input(frame: &mut Frame, app: &App) {
     let input_user = layout[1];
     let input_token = layout[2];
 
-    fn calculate_view(text: &str, width: u16) -> (usize, &str) {
-        let usable_width = width.saturating_sub(4) as usize;
-        let len = text.len();
-
-        let offset = len.saturating_sub(usable_width);
-
-        (offset, &text[offset..])
-    }
-
-    let (user_offset, user_visible) = calculate_view(&app.config.username, input_user.width);
+    let usable_width = |area: Rect| area.width.saturating_sub(4) as usize;
+    let (user_visible, user_cursor) = app.username_input.view(usable_width(input_user), false);
 
     let user_style = if app.focus == Focus::Username {
         Style::default()
-            .fg(Color::Rgb(255, 255, 255))
+            .fg(app.theme.text)
             .add_modifier(Modifier::BOLD)
     } else {
-        Style::default().fg(Color::Rgb(127, 127, 127))
+        Style::default().fg(app.theme.muted)
     };
 
     let username_block = Block::bordered()
@@ -115,18 +114,32 @@ pub fn render_input(frame: &mut Frame, app: &App) {
         input_user,
     );
 
-    let (token_offset, token_visible) = calculate_view(&app.config.token, input_token.width);
+    if !app.asks_token() {
+        frame.set_cursor_position((input_user.x + 2 + user_cursor as u16, input_user.y + 1));
+        return;
+    }
+
+    let (token_visible, token_cursor) = app
+        .token_input
+        .view(usable_width(input_token), !app.reveal_token);
 
     let token_style = if app.focus == Focus::Token {
         Style::default()
-            .fg(Color::Rgb(255, 255, 255))
+            .fg(app.theme.text)
             .add_modifier(Modifier::BOLD)
     } else {
-        Style::default().fg(Color::Rgb(127, 127, 127))
+        Style::default().fg(app.theme.muted)
+    };
+
+    let token_title = match (app.requires_token(), app.reveal_token) {
+        (true, true) => " Token (Ctrl-R to hide) ",
+        (true, false) => " Token (Ctrl-R to reveal) ",
+        (false, true) => " Token, optional (Ctrl-R to hide) ",
+        (false, false) => " Token, optional (Ctrl-R to reveal) ",
     };
 
     let token_block = Block::bordered()
-        .title(" Token ")
+        .title(token_title)
         .border_type(BorderType::Plain)
         .style(token_style);
 
@@ -136,25 +149,119 @@ pub fn render_input(frame: &mut Frame, app: &App) {
     );
 
     match app.focus {
-        Focus::Username => frame.set_cursor_position((
-            input_user.x + 2 + (app.config.username.len() - user_offset) as u16,
-            input_user.y + 1,
-        )),
-        Focus::Token => frame.set_cursor_position((
-            input_token.x + 2 + (app.config.token.len() - token_offset) as u16,
-            input_token.y + 1,
-        )),
+        Focus::Username => {
+            frame.set_cursor_position((input_user.x + 2 + user_cursor as u16, input_user.y + 1))
+        }
+        Focus::Token => {
+            frame.set_cursor_position((input_token.x + 2 + token_cursor as u16, input_token.y + 1))
+        }
+    }
+}
+
+pub fn required_height(app: &App, width: u16) -> u16 {
+    let Some(calendar) = &app.calendar else {
+        return 3 + cells(app, width).rows() as u16;
+    };
+
+    let status_lines = get_status_lines(app, calendar).len();
+    let thresholds = app.scaling.thresholds(calendar);
+    let legend_lines = match legend(&app.theme, &thresholds, calendar_width(app, width)) {
+        Some(_) => 2,
+        None => 0,
+    };
+
+    3 + (cells(app, width).rows() + legend_lines + status_lines) as u16
+}
+
+const LEFT_LABEL_WIDTH: usize = 5;
+
+/// Columns left for the weeks in a terminal `width` columns wide.
+fn week_columns(width: u16) -> usize {
+    (width.saturating_sub(2) as usize).saturating_sub(LEFT_LABEL_WIDTH)
+}
+
+fn cells(app: &App, width: u16) -> Cells {
+    let weeks = app
+        .calendar
+        .as_ref()
+        .map_or(0, |calendar| calendar.weeks.len());
+
+    let cells = Cells::resolve(
+        app.cells,
+        app.config.display.week_width.unwrap_or(3) as usize,
+        weeks,
+        week_columns(width),
+    );
+
+    // Half blocks draw every other day as a background, which can't be bold
+    // or a glyph, so squares of the same width take their place.
+    match cells {
+        Cells::Half if !app.theme.levels_by_color() => Cells::Square,
+        cells => cells,
     }
 }
 
+/// Number of weeks that fit in a terminal `width` columns wide.
+pub fn visible_weeks(app: &App, width: u16) -> usize {
+    cells(app, width).weeks_fitting(week_columns(width))
+}
+
+/// Columns inside the border: the day labels and the displayed weeks.
+fn calendar_width(app: &App, width: u16) -> usize {
+    let (start, end) = app.week_range(visible_weeks(app, width));
+    LEFT_LABEL_WIDTH + cells(app, width).width(end - start)
+}
+
+/// The widest legend that fits in `width` columns: every range next to a
+/// wide cell, then next to a narrow one, then only the lowest and highest
+/// range around the cells, then only the cells. `None` if not even those fit.
+fn legend(theme: &Theme, thresholds: &[u32; 4], width: usize) -> Option<Line<'static>> {
+    let ranges = scale::ranges(thresholds);
+    let cell = |level: usize, symbol: &'static str| {
+        let symbol = match theme.glyphs {
+            Some(glyphs) => glyphs[level].repeat(symbol.chars().count()),
+            None => symbol.to_string(),
+        };
+        Span::styled(symbol, theme.level_style(level))
+    };
+
+    let ranged = |symbol: &'static str, gap: &'static str| {
+        let mut spans = vec![Span::raw(" ")];
+        for (level, range) in ranges.iter().enumerate() {
+            spans.push(Span::raw(gap));
+            spans.push(cell(level, symbol));
+            spans.push(Span::raw(format!("{}{} ", gap, range)));
+        }
+        Line::from(spans)
+    };
+
+    let bounded = || {
+        let mut spans = vec![Span::raw(format!(" {} ", ranges[0]))];
+        spans.extend((0..ranges.len()).map(|level| cell(level, "■")));
+        spans.push(Span::raw(format!(" {}", ranges[4])));
+        Line::from(spans)
+    };
+
+    let cells_only = || {
+        let mut spans = vec![Span::raw(" ")];
+        spans.extend((0..ranges.len()).map(|level| cell(level, "■")));
+        Line::from(spans)
+    };
+
+    [ranged("██", " "), ranged("■", ""), bounded(), cells_only()]
+        .into_iter()
+        .find(|line| line.width() <= width)
+}
+
 pub fn render(frame: &mut Frame, app: &App) {
     const MIN_WIDTH: u16 = 30;
-    const MIN_HEIGHT: u16 = 12;
-
     let area = frame.area();
+    let min_height = required_height(app, area.width);
+    // At least one week has to fit, which wide full cells may not.
+    let min_width = MIN_WIDTH.max((2 + LEFT_LABEL_WIDTH + cells(app, area.width).width(1)) as u16);
 
-    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
-        render_error(frame, area, MIN_WIDTH, MIN_HEIGHT);
+    if area.width < min_width || area.height < min_height {
+        render_error(frame, &app.theme, area, min_width, min_height);
         return;
     }
 
@@ -166,16 +273,20 @@ pub fn render(frame: &mut Frame, app: &App) {
         return;
     }
 
-    let available_width = area.width.saturating_sub(2) as usize;
-    let left_label_width = 5;
-    let week_width = 3;
-    let max_weeks = (available_width - left_label_width) / week_width;
+    let status_lines = get_status_lines(app, calendar);
+
+    let cells = cells(app, area.width);
+    let theme = &app.theme;
+    let thresholds = app.scaling.thresholds(calendar);
+    let max_weeks = visible_weeks(app, area.width);
 
-    let start_week_idx = weeks.len().saturating_sub(max_weeks);
-    let displayed_weeks = &weeks[start_week_idx..];
+    let (start_week_idx, end_week_idx) = app.week_range(max_weeks);
+    let displayed_weeks = &weeks[start_week_idx..end_week_idx];
 
-    let calendar_width = (left_label_width + displayed_weeks.len() * week_width) as u16;
-    let calendar_height = 10;
+    let calendar_width = calendar_width(app, area.width) as u16;
+    let legend = legend(theme, &thresholds, calendar_width as usize);
+    let legend_lines = if legend.is_some() { 2 } else { 0 };
+    let calendar_height = 1 + (cells.rows() + legend_lines + status_lines.len()) as u16;
 
     let calendar_area = Rect {
         x: area.x + (area.width.saturating_sub(calendar_width + 2)) / 2,
@@ -184,10 +295,26 @@ pub fn render(frame: &mut Frame, app: &App) {
         height: calendar_height + 2,
     };
 
-    let block = Block::default()
-        .title(format!(" trexanh - @{} ", app.config.username))
+    let title = match app.fetched_at {
+        Some(fetched_at) => format!(
+            " trexanh - @{} - updated {} ",
+            app.profile.username,
+            duration::format_age(fetched_at)
+        ),
+        None => format!(" trexanh - @{} ", app.profile.username),
+    };
+
+    let mut block = Block::default()
+        .title(title)
         .borders(Borders::ALL)
-        .style(Style::default().fg(Color::Rgb(255, 255, 255)));
+        .border_style(Style::default().fg(theme.border))
+        .title_style(Style::default().fg(theme.text));
+
+    if displayed_weeks.len() < weeks.len() {
+        block = block.title_bottom(
+            Line::from(get_scroll_position(weeks, start_week_idx, end_week_idx)).right_aligned(),
+        );
+    }
 
     let inner = block.inner(calendar_area);
     frame.render_widget(block, calendar_area);
@@ -197,82 +324,381 @@ pub fn render(frame: &mut Frame, app: &App) {
     lines.push(Line::from(vec![
         Span::raw("     "),
         Span::styled(
-            get_month_labels(displayed_weeks),
-            Style::default().fg(Color::Rgb(255, 255, 255)),
+            get_month_labels(displayed_weeks, cells),
+            Style::default().fg(theme.text),
         ),
     ]));
 
-    let left_labels = ["Mon", "Wed", "Fri"];
-    let left_label_positions = [1, 3, 5];
+    let grid = Grid {
+        app,
+        thresholds,
+        selected: app.cursor.map(|date| date.format("%Y-%m-%d").to_string()),
+    };
 
-    for day_idx in 0..7 {
-        let mut line_spans = vec![];
+    for row in 0..cells.rows() {
+        let mut line_spans = vec![match cells.row_label(row) {
+            Some(label) => Span::styled(format!(" {:>3} ", label), Style::default().fg(theme.text)),
+            None => Span::raw("     "),
+        }];
+
+        match cells {
+            Cells::Full { week_width } => {
+                for (week_idx, week) in displayed_weeks.iter().enumerate() {
+                    line_spans.push(grid.full_cell(week, row, week_width - 1));
+                    if week_idx < displayed_weeks.len() - 1 {
+                        line_spans.push(Span::raw(" "));
+                    }
+                }
+            }
+            Cells::Square => {
+                line_spans.extend(
+                    displayed_weeks
+                        .iter()
+                        .map(|week| grid.square_cell(week, row)),
+                );
+            }
+            Cells::Half => {
+                line_spans.extend(displayed_weeks.iter().map(|week| grid.half_cell(week, row)));
+            }
+            Cells::Braille => {
+                line_spans.extend(
+                    displayed_weeks
+                        .chunks(2)
+                        .map(|weeks| grid.braille_cell(weeks, row)),
+                );
+            }
+        }
 
-        if left_label_positions.contains(&day_idx) {
-            let label = left_labels[left_label_positions
-                .iter()
-                .position(|&x| x == day_idx)
-                .unwrap()];
-            line_spans.push(Span::styled(
-                format!(" {:>3} ", label),
-                Style::default().fg(Color::Rgb(255, 255, 255)),
-            ));
+        lines.push(Line::from(line_spans));
+    }
+
+    if let Some(legend) = legend {
+        lines.push(Line::from(""));
+        lines.push(legend);
+    }
+    // Lines wider than the calendar are cut off rather than wrapped, since
+    // the height only has room for one row each.
+    lines.extend(status_lines);
+
+    let paragraph = Paragraph::new(lines).style(Style::default().fg(theme.text));
+
+    frame.render_widget(paragraph, inner);
+}
+
+fn get_status_lines(app: &App, calendar: &ContributionCalendar) -> Vec<Line<'static>> {
+    let mut lines = vec![];
+
+    if let Some(detail) = get_cursor_detail(app, calendar) {
+        lines.push(Line::from(Span::styled(
+            detail,
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    if let Some(error) = &app.last_error {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  ⚠ Background refresh failed {}: {}",
+                duration::format_age(error.at),
+                summarize_error(&error.message)
+            ),
+            Style::default()
+                .fg(app.theme.error)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    if let Some(warning) = app.warnings.first() {
+        let more = match app.warnings.len() {
+            1 => String::new(),
+            count => format!(" (and {} more)", count - 1),
+        };
+        lines.push(Line::from(Span::styled(
+            format!("  ⚠ {}{}", summarize_error(warning), more),
+            Style::default().fg(app.theme.error),
+        )));
+    }
+
+    if let Some(summary) = diff::summary(&app.changes) {
+        lines.push(Line::from(format!("  {}", summary)));
+    }
+
+    let Some(goal) = &app.config.goal else {
+        return lines;
+    };
+    let Some((week, today)) = stats::today(calendar) else {
+        return lines;
+    };
+
+    let mut progress = vec![];
+    if let Some(daily) = goal.daily {
+        progress.push(format_progress("Today", today.contribution_count, daily));
+    }
+    if let Some(weekly) = goal.weekly {
+        progress.push(format_progress(
+            "This week",
+            stats::week_total(week),
+            weekly,
+        ));
+    }
+    if !progress.is_empty() {
+        lines.push(Line::from(format!("  {}", progress.join("   "))));
+    }
+
+    let daily_goal = goal.daily.unwrap_or(1);
+    if app.watching
+        && today.contribution_count < daily_goal
+        && Local::now().hour() >= goal.warn_hour
+    {
+        let streak = stats::streak_before_today(calendar, daily_goal);
+        if streak > 0 {
+            lines.push(Line::from(Span::styled(
+                format!("  ⚠ {}-day streak at risk, goal not met today", streak),
+                Style::default()
+                    .fg(app.theme.error)
+                    .add_modifier(Modifier::BOLD),
+            )));
+        }
+    }
+
+    lines
+}
+
+/// Dates of the first and last shown days, with arrows on the sides that
+/// have more weeks.
+fn get_scroll_position(weeks: &[Week], start: usize, end: usize) -> String {
+    let date = |week: &Week, last: bool| {
+        let day = if last {
+            week.contribution_days.last()
         } else {
-            line_spans.push(Span::raw("     "));
+            week.contribution_days.first()
+        };
+        day.and_then(|day| day.date.parse::<NaiveDate>().ok())
+            .map(|date| date.format("%b %-d %Y").to_string())
+            .unwrap_or_default()
+    };
+
+    format!(
+        " {}{} – {}{} ",
+        if start > 0 { "◀ " } else { "" },
+        date(&weeks[start], false),
+        date(&weeks[end - 1], true),
+        if end < weeks.len() { " ▶" } else { "" }
+    )
+}
+
+/// Draws one row of the graph's cells in the current cell mode.
+struct Grid<'a> {
+    app: &'a App,
+    thresholds: [u32; 4],
+    selected: Option<String>,
+}
+
+impl Grid<'_> {
+    fn is_highlighted(&self, day: &ContributionDay) -> bool {
+        self.app.is_highlighting() && self.app.is_changed(&day.date)
+    }
+
+    fn style(&self, day: &ContributionDay) -> Style {
+        if self.is_highlighted(day) {
+            Style::default().fg(self.app.theme.changed)
+        } else {
+            self.app.theme.level_style(self.level(day))
         }
+    }
 
-        for (week_idx, week) in displayed_weeks.iter().enumerate() {
-            if let Some(day) = week.contribution_days.get(day_idx) {
-                let color = get_contribution_color(day.contribution_count);
-                line_spans.push(Span::styled("██", Style::default().fg(color)));
-            } else {
-                line_spans.push(Span::raw("  "));
-            }
+    /// Whether the day gets the goal marker, which changed days leave out
+    /// while they are highlighted.
+    fn shows_goal(&self, week: &Week, day: &ContributionDay) -> bool {
+        !self.is_highlighted(day)
+            && self
+                .app
+                .config
+                .goal
+                .as_ref()
+                .is_some_and(|goal| goal.is_met(day.contribution_count, stats::week_total(week)))
+    }
 
-            if week_idx < displayed_weeks.len() - 1 {
-                line_spans.push(Span::raw(" "));
-            }
+    /// A `•` in the goal color over the level color, which becomes the
+    /// background. Without colors the dot takes the last column, after the
+    /// level's glyph if there's room.
+    fn goal_cell(&self, day: &ContributionDay, width: usize) -> Span<'static> {
+        let theme = &self.app.theme;
+        let level = self.level(day);
+
+        match theme.glyphs {
+            Some(glyphs) => Span::raw(format!(
+                "{}•",
+                glyphs[level].repeat(width.saturating_sub(1))
+            )),
+            None => Span::styled(
+                format!("{:^width$}", "•"),
+                theme
+                    .level_style(level)
+                    .fg(theme.goal)
+                    .bg(theme.levels[level]),
+            ),
         }
+    }
 
-        lines.push(Line::from(line_spans));
+    fn level(&self, day: &ContributionDay) -> usize {
+        scale::level(&self.thresholds, day.contribution_count)
     }
 
-    lines.push(Line::from(""));
+    /// `block`, or the day's glyph when there are no colors.
+    fn symbol(&self, day: &ContributionDay, block: &'static str) -> &'static str {
+        self.app.theme.level_symbol(self.level(day), block)
+    }
 
-    let legend_levels = [0, 1, 4, 7, 11];
+    /// Marks the selected day with a background in the text color, or
+    /// reversed without colors.
+    fn select(&self, style: Style) -> Style {
+        if self.app.theme.glyphs.is_some() {
+            style.add_modifier(Modifier::REVERSED)
+        } else {
+            style.bg(self.app.theme.text)
+        }
+    }
 
-    let mut legend_spans = vec![Span::raw("  Less ")];
+    fn is_selected(&self, day: &ContributionDay) -> bool {
+        self.selected.as_deref() == Some(day.date.as_str())
+    }
 
-    for &level in &legend_levels {
-        legend_spans.push(Span::styled(
-            "██",
-            Style::default().fg(get_contribution_color(level)),
-        ));
-        legend_spans.push(Span::raw(" "));
+    fn full_cell(&self, week: &Week, row: usize, width: usize) -> Span<'static> {
+        let Some(day) = week.contribution_days.get(row) else {
+            return Span::raw(" ".repeat(width));
+        };
+        let style = self.style(day);
+
+        if self.is_selected(day) {
+            Span::styled(cursor_cell(width), self.select(style))
+        } else if self.shows_goal(week, day) {
+            self.goal_cell(day, width)
+        } else {
+            Span::styled(self.symbol(day, "█").repeat(width), style)
+        }
     }
 
-    legend_spans.push(Span::raw("More"));
+    fn square_cell(&self, week: &Week, row: usize) -> Span<'static> {
+        let Some(day) = week.contribution_days.get(row) else {
+            return Span::raw(" ");
+        };
+        let mut style = self.style(day);
+        if self.is_selected(day) {
+            style = self.select(style);
+        } else if self.shows_goal(week, day) {
+            return self.goal_cell(day, 1);
+        }
 
-    lines.push(Line::from(legend_spans));
+        Span::styled(self.symbol(day, "■"), style)
+    }
 
-    let paragraph = Paragraph::new(lines)
-        .style(Style::default().fg(Color::Rgb(255, 255, 255)))
-        .wrap(Wrap { trim: false });
+    /// The upper half is the first of the row's two days. The selected day
+    /// takes the text color. Only used when the levels differ by color, as
+    /// the lower half is a background, and too small for the goal marker.
+    fn half_cell(&self, week: &Week, row: usize) -> Span<'static> {
+        let color = |day: &ContributionDay| {
+            if self.is_selected(day) {
+                self.app.theme.text
+            } else {
+                self.style(day).fg.unwrap_or(Color::Reset)
+            }
+        };
+        let top = week.contribution_days.get(row * 2).map(color);
+        let bottom = week.contribution_days.get(row * 2 + 1).map(color);
+
+        match (top, bottom) {
+            (Some(top), Some(bottom)) => Span::styled("▀", Style::default().fg(top).bg(bottom)),
+            (Some(top), None) => Span::styled("▀", Style::default().fg(top)),
+            (None, Some(bottom)) => Span::styled("▄", Style::default().fg(bottom)),
+            (None, None) => Span::raw(" "),
+        }
+    }
 
-    frame.render_widget(paragraph, inner);
+    /// Two weeks of four days, with a dot for each day with contributions,
+    /// colored after the busiest of them. Goals aren't marked.
+    fn braille_cell(&self, weeks: &[Week], row: usize) -> Span<'static> {
+        const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+        let mut bits = 0;
+        let mut busiest: Option<&ContributionDay> = None;
+        let mut selected = false;
+
+        for (column, week) in weeks.iter().enumerate() {
+            for (dot, day) in week
+                .contribution_days
+                .iter()
+                .skip(row * 4)
+                .take(4)
+                .enumerate()
+            {
+                selected |= self.is_selected(day);
+                if day.contribution_count == 0 {
+                    continue;
+                }
+                bits |= DOTS[column][dot];
+                if busiest.is_none_or(|busiest| day.contribution_count > busiest.contribution_count)
+                {
+                    busiest = Some(day);
+                }
+            }
+        }
+
+        let symbol = char::from_u32(0x2800 + bits).unwrap_or(' ').to_string();
+        let mut style = match busiest {
+            Some(day) => self.style(day),
+            None => Style::default(),
+        };
+        if selected {
+            style = self.select(style);
+        }
+
+        Span::styled(symbol, style)
+    }
 }
 
-fn get_contribution_color(count: u32) -> Color {
-    match count {
-        0 => Color::Rgb(55, 55, 55),
-        1..=3 => Color::Rgb(90, 140, 120),
-        4..=6 => Color::Rgb(120, 180, 130),
-        7..=10 => Color::Rgb(160, 210, 150),
-        _ => Color::Rgb(220, 240, 170),
+/// The selected cell, framed by bars in the text color on both sides.
+fn cursor_cell(width: usize) -> String {
+    match width {
+        1 => "▪".to_string(),
+        _ => format!("▐{}▌", "█".repeat(width - 2)),
     }
 }
 
-fn get_month_labels(weeks: &[crate::models::Week]) -> String {
+fn get_cursor_detail(app: &App, calendar: &ContributionCalendar) -> Option<String> {
+    let date = app.cursor?;
+    let (week_idx, day_idx) = stats::find_day(calendar, date)?;
+    let count = calendar.weeks[week_idx].contribution_days[day_idx].contribution_count;
+    let (rank, days) = stats::rank(calendar, count);
+
+    Some(format!(
+        "  {}: {} contribution{}, #{} of {} days",
+        date.format("%a %Y-%m-%d"),
+        count,
+        if count == 1 { "" } else { "s" },
+        rank,
+        days
+    ))
+}
+
+/// Keeps the error banner on one line by showing only the outermost context
+/// of the error chain; the full message is in the updater log.
+fn summarize_error(message: &str) -> String {
+    const MAX_CHARS: usize = 40;
+
+    let outer = message.split(": ").next().unwrap_or(message);
+    match outer.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}…", &outer[..end]),
+        None => outer.to_string(),
+    }
+}
+
+fn format_progress(label: &str, count: u32, target: u32) -> String {
+    let mark = if count >= target { " ✓" } else { "" };
+    format!("{}: {}/{}{}", label, count, target, mark)
+}
+
+fn get_month_labels(weeks: &[Week], cells: Cells) -> String {
     let mut month_label = Vec::new();
     let mut last_month: Option<u32> = None;
 
@@ -303,9 +729,13 @@ fn get_month_labels(weeks: &[crate::models::Week]) -> String {
     let mut label = String::new();
 
     for (week_pos, month) in month_label.iter() {
-        let target_pos = week_pos * 3;
+        let target_pos = cells.column(*week_pos);
         let current_len = label.len();
 
+        // Dense layouts have less room than a label per month.
+        if current_len > 0 && target_pos <= current_len {
+            continue;
+        }
         if target_pos > current_len {
             label.push_str(&" ".repeat(target_pos - current_len));
         }
@@ -315,3 +745,110 @@ fn get_month_labels(weeks: &[crate::models::Week]) -> String {
 
     label
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Support;
+
+    fn text(line: &Line) -> String {
+        line.spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn legend_shortens_to_fit() {
+        let theme = Theme::default();
+        let thresholds = [1, 4, 7, 11];
+        let legend = |width| legend(&theme, &thresholds, width).map(|line| text(&line));
+
+        assert_eq!(
+            legend(80).unwrap(),
+            "  ██ 0  ██ 1-3  ██ 4-6  ██ 7-10  ██ 11+ "
+        );
+        assert_eq!(legend(30).unwrap(), " ■0 ■1-3 ■4-6 ■7-10 ■11+ ");
+        assert_eq!(legend(12).unwrap(), " 0 ■■■■■ 11+");
+        assert_eq!(legend(8).unwrap(), " ■■■■■");
+        assert_eq!(legend(5), None);
+    }
+
+    #[test]
+    fn legend_never_exceeds_the_width() {
+        let theme = Theme::default();
+        let thresholds = [1, 100, 1000, 10000];
+
+        for width in 0..60 {
+            if let Some(line) = legend(&theme, &thresholds, width) {
+                assert!(line.width() <= width);
+            }
+        }
+    }
+
+    #[test]
+    fn legend_uses_the_glyphs_without_colors() {
+        let theme = Theme::default().adapt(Support::None);
+        let legend = legend(&theme, &[1, 4, 7, 11], 80).unwrap();
+
+        assert_eq!(text(&legend), "  ·· 0  ░░ 1-3  ▒▒ 4-6  ▓▓ 7-10  ██ 11+ ");
+    }
+
+    fn goal_app(theme: Theme) -> App {
+        let config = crate::config::Config {
+            goal: Some(toml::from_str("daily = 3").unwrap()),
+            ..Default::default()
+        };
+        let mut app = App::new(config, Default::default());
+        app.theme = theme;
+        app
+    }
+
+    fn week(counts: &[u32]) -> Week {
+        Week {
+            contribution_days: counts
+                .iter()
+                .map(|&count| ContributionDay {
+                    date: String::new(),
+                    contribution_count: count,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn met_goals_keep_the_level_color() {
+        let theme = Theme::default();
+        let app = goal_app(theme);
+        let grid = Grid {
+            app: &app,
+            thresholds: [1, 4, 7, 11],
+            selected: None,
+        };
+        let week = week(&[1, 5]);
+
+        let unmet = grid.full_cell(&week, 0, 2);
+        assert_eq!(unmet.content, "██");
+        assert_eq!(unmet.style.fg, Some(theme.levels[1]));
+
+        let met = grid.full_cell(&week, 1, 2);
+        assert_eq!(met.content, "• ");
+        assert_eq!(met.style.fg, Some(theme.goal));
+        assert_eq!(met.style.bg, Some(theme.levels[2]));
+    }
+
+    #[test]
+    fn met_goals_show_without_colors() {
+        let app = goal_app(Theme::default().adapt(Support::None));
+        let grid = Grid {
+            app: &app,
+            thresholds: [1, 4, 7, 11],
+            selected: None,
+        };
+        let week = week(&[1, 5]);
+
+        assert_eq!(grid.full_cell(&week, 0, 2).content, "░░");
+        assert_eq!(grid.full_cell(&week, 1, 2).content, "▒•");
+        assert_eq!(grid.square_cell(&week, 1).content, "•");
+    }
+}